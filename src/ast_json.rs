@@ -0,0 +1,672 @@
+//! A JSON representation of the AST, so trees can be exchanged with external
+//! tools without pulling in serde.
+//!
+//! Nodes are objects with a "type" field naming the Expr variant and one field
+//! per operand. Literal values map onto the corresponding JSON values, with nil
+//! as null, alongside a "lexeme" field if the source spelling was kept. Tokens
+//! are objects with their type, lexeme, line and column.
+
+use std::{iter::Peekable, str::CharIndices};
+
+use crate::{
+    diagnostic::escape,
+    expr::{Expr, ExprVisitor},
+    scanner::Scanner,
+    stmt::{Stmt, StmtVisitor},
+    token::{Literal, Token},
+};
+
+pub struct AstJsonPrinter {}
+
+impl AstJsonPrinter {
+    pub fn print(&mut self, expression: &Expr) -> String {
+        expression.accept(self)
+    }
+
+    /// Print a program as an array of statements
+    pub fn print_program(&mut self, statements: &[Stmt]) -> String {
+        let statements = statements
+            .iter()
+            .map(|statement| statement.accept(self))
+            .collect::<Vec<String>>();
+        format!("[{}]", statements.join(","))
+    }
+
+    fn token(token: &Token) -> String {
+        format!(
+            r#"{{"type":"{:?}","lexeme":"{}","line":{},"column":{}}}"#,
+            token.token_type,
+            escape(&token.lexeme),
+            token.line,
+            token.column
+        )
+    }
+}
+
+impl StmtVisitor<String> for AstJsonPrinter {
+    fn visit_expression(&mut self, expression: &Expr) -> String {
+        format!(
+            r#"{{"type":"Expression","expression":{}}}"#,
+            expression.accept(self)
+        )
+    }
+
+    fn visit_print(&mut self, expressions: &[Expr]) -> String {
+        format!(
+            r#"{{"type":"Print","expressions":[{}]}}"#,
+            expressions
+                .iter()
+                .map(|e| e.accept(self))
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    }
+}
+
+impl ExprVisitor<String> for AstJsonPrinter {
+    fn visit_binary(&mut self, lhs: &Expr, operator: &Token, rhs: &Expr) -> String {
+        format!(
+            r#"{{"type":"Binary","lhs":{},"operator":{},"rhs":{}}}"#,
+            lhs.accept(self),
+            Self::token(operator),
+            rhs.accept(self)
+        )
+    }
+
+    fn visit_logical(&mut self, lhs: &Expr, operator: &Token, rhs: &Expr) -> String {
+        format!(
+            r#"{{"type":"Logical","lhs":{},"operator":{},"rhs":{}}}"#,
+            lhs.accept(self),
+            Self::token(operator),
+            rhs.accept(self)
+        )
+    }
+
+    fn visit_ternary(
+        &mut self,
+        lhs: &Expr,
+        lho: &Token,
+        mhs: &Expr,
+        rho: &Token,
+        rhs: &Expr,
+    ) -> String {
+        format!(
+            r#"{{"type":"Ternary","lhs":{},"lho":{},"mhs":{},"rho":{},"rhs":{}}}"#,
+            lhs.accept(self),
+            Self::token(lho),
+            mhs.accept(self),
+            Self::token(rho),
+            rhs.accept(self)
+        )
+    }
+
+    fn visit_call(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> String {
+        format!(
+            r#"{{"type":"Call","callee":{},"paren":{},"arguments":[{}]}}"#,
+            callee.accept(self),
+            Self::token(paren),
+            arguments
+                .iter()
+                .map(|a| a.accept(self))
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    }
+
+    fn visit_get(&mut self, object: &Expr, name: &Token) -> String {
+        format!(
+            r#"{{"type":"Get","object":{},"name":{}}}"#,
+            object.accept(self),
+            Self::token(name)
+        )
+    }
+
+    fn visit_grouping(&mut self, expression: &Expr) -> String {
+        format!(
+            r#"{{"type":"Grouping","expression":{}}}"#,
+            expression.accept(self)
+        )
+    }
+
+    fn visit_literal(&mut self, value: &Literal, lexeme: &Option<String>) -> String {
+        // JSON has no characters, so they go in a field of their own to tell
+        // them apart from one-character strings
+        let (field, value) = match value {
+            Literal::String(s) => ("value", format!("\"{}\"", escape(s))),
+            Literal::Char(c) => ("char", format!("\"{}\"", escape(&c.to_string()))),
+            Literal::Number(n) => ("value", n.to_string()),
+            Literal::Bool(b) => ("value", b.to_string()),
+            Literal::Nil() => ("value", "null".into()),
+        };
+        match lexeme {
+            Some(lexeme) => format!(
+                r#"{{"type":"Literal","{}":{},"lexeme":"{}"}}"#,
+                field,
+                value,
+                escape(lexeme)
+            ),
+            None => format!(r#"{{"type":"Literal","{}":{}}}"#, field, value),
+        }
+    }
+
+    fn visit_unary(&mut self, operator: &Token, operand: &Expr) -> String {
+        format!(
+            r#"{{"type":"Unary","operator":{},"operand":{}}}"#,
+            Self::token(operator),
+            operand.accept(self)
+        )
+    }
+
+    fn visit_variable(&mut self, name: &Token) -> String {
+        format!(r#"{{"type":"Variable","name":{}}}"#, Self::token(name))
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt], value: &Expr) -> String {
+        format!(
+            r#"{{"type":"Block","statements":[{}],"value":{}}}"#,
+            statements
+                .iter()
+                .map(|s| s.accept(self))
+                .collect::<Vec<String>>()
+                .join(","),
+            value.accept(self)
+        )
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("{message}")]
+pub struct JsonError {
+    pub message: String,
+}
+
+impl JsonError {
+    fn new(message: String) -> Self {
+        JsonError { message }
+    }
+}
+
+/// Reconstruct an Expr from the format produced by AstJsonPrinter.
+pub fn from_json(json: &str) -> Result<Expr, JsonError> {
+    let mut reader = JsonReader {
+        chars: json.char_indices().peekable(),
+        length: json.len(),
+    };
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    if let Some((offset, _)) = reader.chars.peek() {
+        return Err(JsonError::new(format!(
+            "Trailing characters at offset {}",
+            offset
+        )));
+    }
+    expr(&value)
+}
+
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn describe(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool(_) => "a bool",
+            Json::Number(_) => "a number",
+            Json::String(_) => "a string",
+            Json::Array(_) => "an array",
+            Json::Object(_) => "an object",
+        }
+    }
+
+    fn field(&self, name: &str) -> Result<&Json, JsonError> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+                .ok_or_else(|| JsonError::new(format!("Missing field '{}'", name))),
+            other => Err(JsonError::new(format!(
+                "Expected an object, found {}",
+                other.describe()
+            ))),
+        }
+    }
+
+    fn as_str(&self) -> Result<&str, JsonError> {
+        match self {
+            Json::String(s) => Ok(s),
+            other => Err(JsonError::new(format!(
+                "Expected a string, found {}",
+                other.describe()
+            ))),
+        }
+    }
+
+    fn as_array(&self) -> Result<&[Json], JsonError> {
+        match self {
+            Json::Array(elements) => Ok(elements),
+            other => Err(JsonError::new(format!(
+                "Expected an array, found {}",
+                other.describe()
+            ))),
+        }
+    }
+
+    fn as_usize(&self) -> Result<usize, JsonError> {
+        match self {
+            Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+            other => Err(JsonError::new(format!(
+                "Expected a non-negative integer, found {}",
+                other.describe()
+            ))),
+        }
+    }
+}
+
+fn expr(json: &Json) -> Result<Expr, JsonError> {
+    match json.field("type")?.as_str()? {
+        "Binary" => Ok(Expr::new_binary(
+            expr(json.field("lhs")?)?,
+            token(json.field("operator")?)?,
+            expr(json.field("rhs")?)?,
+        )),
+        "Logical" => Ok(Expr::new_logical(
+            expr(json.field("lhs")?)?,
+            token(json.field("operator")?)?,
+            expr(json.field("rhs")?)?,
+        )),
+        "Ternary" => Ok(Expr::new_ternary(
+            expr(json.field("lhs")?)?,
+            token(json.field("lho")?)?,
+            expr(json.field("mhs")?)?,
+            token(json.field("rho")?)?,
+            expr(json.field("rhs")?)?,
+        )),
+        "Call" => Ok(Expr::new_call(
+            expr(json.field("callee")?)?,
+            token(json.field("paren")?)?,
+            json.field("arguments")?
+                .as_array()?
+                .iter()
+                .map(expr)
+                .collect::<Result<Vec<Expr>, JsonError>>()?,
+        )),
+        "Get" => Ok(Expr::new_get(
+            expr(json.field("object")?)?,
+            token(json.field("name")?)?,
+        )),
+        "Grouping" => Ok(Expr::new_grouping(expr(json.field("expression")?)?)),
+        "Literal" => Ok(Expr::new_literal(
+            match json.field("char") {
+                Ok(c) => char_literal(c)?,
+                Err(_) => literal(json.field("value")?)?,
+            },
+            match json.field("lexeme") {
+                Ok(lexeme) => Some(lexeme.as_str()?.to_string()),
+                Err(_) => None,
+            },
+        )),
+        "Unary" => Ok(Expr::new_unary(
+            token(json.field("operator")?)?,
+            expr(json.field("operand")?)?,
+        )),
+        "Variable" => Ok(Expr::new_variable(token(json.field("name")?)?)),
+        "Block" => Ok(Expr::new_block(
+            json.field("statements")?
+                .as_array()?
+                .iter()
+                .map(stmt)
+                .collect::<Result<Vec<Stmt>, JsonError>>()?,
+            expr(json.field("value")?)?,
+        )),
+        other => Err(JsonError::new(format!("Unknown node type '{}'", other))),
+    }
+}
+
+fn stmt(json: &Json) -> Result<Stmt, JsonError> {
+    match json.field("type")?.as_str()? {
+        "Expression" => Ok(Stmt::new_expression(expr(json.field("expression")?)?)),
+        "Print" => Ok(Stmt::new_print(
+            json.field("expressions")?
+                .as_array()?
+                .iter()
+                .map(expr)
+                .collect::<Result<Vec<Expr>, JsonError>>()?,
+        )),
+        other => Err(JsonError::new(format!(
+            "Unknown statement type '{}'",
+            other
+        ))),
+    }
+}
+
+fn literal(json: &Json) -> Result<Literal, JsonError> {
+    match json {
+        Json::Null => Ok(Literal::Nil()),
+        Json::Bool(b) => Ok(Literal::Bool(*b)),
+        Json::Number(n) => Ok(Literal::Number(*n)),
+        Json::String(s) => Ok(Literal::String(s.clone())),
+        other => Err(JsonError::new(format!(
+            "Expected a literal value, found {}",
+            other.describe()
+        ))),
+    }
+}
+
+fn char_literal(json: &Json) -> Result<Literal, JsonError> {
+    let string = json.as_str()?;
+    let mut chars = string.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Literal::Char(c)),
+        _ => Err(JsonError::new(format!(
+            "Expected a single character, found \"{}\"",
+            string
+        ))),
+    }
+}
+
+fn token(json: &Json) -> Result<Token, JsonError> {
+    let token_type = json.field("type")?.as_str()?;
+    let lexeme = json.field("lexeme")?.as_str()?;
+
+    // Only operators end up as tokens in the AST, and an operator's type is
+    // fully determined by its lexeme, so rather than keeping a second table of
+    // token type names the lexeme is simply scanned again.
+    let scanned = match Scanner::new(lexeme).scan_tokens() {
+        Ok(tokens) if tokens.len() == 2 => tokens[0].token_type,
+        _ => {
+            return Err(JsonError::new(format!(
+                "'{}' is not a single token",
+                lexeme
+            )))
+        }
+    };
+    if format!("{:?}", scanned) != token_type {
+        return Err(JsonError::new(format!(
+            "Token type {} does not match lexeme '{}'",
+            token_type, lexeme
+        )));
+    }
+
+    Ok(Token::new(
+        scanned,
+        lexeme,
+        json.field("line")?.as_usize()?,
+        json.field("column")?.as_usize()?,
+    ))
+}
+
+/// Just enough of a JSON reader to get the printer's output back in.
+struct JsonReader<'json> {
+    chars: Peekable<CharIndices<'json>>,
+    length: usize,
+}
+
+impl<'json> JsonReader<'json> {
+    fn offset(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map(|(offset, _)| *offset)
+            .unwrap_or(self.length)
+    }
+
+    fn error(&mut self, message: &str) -> JsonError {
+        JsonError::new(format!("{} at offset {}", message, self.offset()))
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        self.chars.next().map(|(_, c)| c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.advance();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", expected)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(self.error(&format!("Unexpected character '{}'", c))),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in keyword.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(&format!("Expected '{}'", keyword)));
+            }
+            self.advance();
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let offset = self.offset();
+        let mut number = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+                number.push(c);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        number.parse().map(Json::Number).map_err(|_| {
+            JsonError::new(format!("Invalid number '{}' at offset {}", number, offset))
+        })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(result),
+                Some('\\') => result.push(self.escape()?),
+                Some(c) => result.push(c),
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, JsonError> {
+        match self.advance() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('/') => Ok('/'),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{c}'),
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some('u') => {
+                let high = self.hex4()?;
+                // Characters outside the BMP come in as a surrogate pair
+                let code = match high {
+                    0xD800..=0xDBFF => {
+                        self.keyword("\\u", Json::Null)?;
+                        match self.hex4()? {
+                            low @ 0xDC00..=0xDFFF => {
+                                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                            }
+                            _ => return Err(self.error("Invalid surrogate pair")),
+                        }
+                    }
+                    _ => high,
+                };
+                char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))
+            }
+            _ => Err(self.error("Invalid escape sequence")),
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.advance().and_then(|c| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err(self.error("Invalid unicode escape")),
+            }
+        }
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.advance();
+            return Ok(Json::Array(elements));
+        }
+        loop {
+            elements.push(self.value()?);
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(elements)),
+                _ => return Err(self.error("Expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.advance();
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> Expr {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        Parser::new(&tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn print_literal() {
+        let expr = Expr::new_literal(Literal::from("say \"hi\"\n"), None);
+        assert_eq!(
+            AstJsonPrinter {}.print(&expr),
+            r#"{"type":"Literal","value":"say \"hi\"\n"}"#
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let expr = parse("-(1.50 + 2) * \"three\" ? nil : !true, false, 'a'");
+        let json = AstJsonPrinter {}.print(&expr);
+        assert_eq!(from_json(&json).unwrap(), expr);
+    }
+
+    #[test]
+    fn round_trip_chars() {
+        let expr = parse("'a' + \"a\"");
+        let json = AstJsonPrinter {}.print(&expr);
+        assert!(json.contains(r#"{"type":"Literal","char":"a"}"#));
+        assert_eq!(from_json(&json).unwrap(), expr);
+
+        let error = from_json(r#"{"type": "Literal", "char": "ab"}"#).unwrap_err();
+        assert_eq!(error.message, "Expected a single character, found \"ab\"");
+    }
+
+    #[test]
+    fn round_trip_calls() {
+        let expr = parse("a.b(c, 1)(), d.e ?? nil");
+        let json = AstJsonPrinter {}.print(&expr);
+        assert_eq!(from_json(&json).unwrap(), expr);
+    }
+
+    #[test]
+    fn round_trip_blocks() {
+        let tokens = Scanner::new("{ print 1, 2; f(); 3 } + 4")
+            .scan_tokens()
+            .unwrap();
+        let expr = Parser::new(&tokens)
+            .block_expressions(true)
+            .parse()
+            .unwrap();
+        let json = AstJsonPrinter {}.print(&expr);
+        assert_eq!(from_json(&json).unwrap(), expr);
+    }
+
+    #[test]
+    fn malformed_json() {
+        let error = from_json(r#"{"type": "Literal", "value": 1"#).unwrap_err();
+        assert_eq!(error.message, "Expected ',' or '}' at offset 30");
+    }
+
+    #[test]
+    fn string_escapes() {
+        let expr = from_json(r#"{"type": "Literal", "value": "\u00e9\ud83d\ude00\t"}"#).unwrap();
+        assert_eq!(
+            expr,
+            Expr::new_literal(Literal::from("\u{e9}\u{1f600}\t"), None)
+        );
+    }
+
+    #[test]
+    fn unknown_node_type() {
+        let error = from_json(r#"{"type": "Lambda"}"#).unwrap_err();
+        assert_eq!(error.message, "Unknown node type 'Lambda'");
+    }
+
+    #[test]
+    fn mismatched_token() {
+        let error = from_json(
+            r#"{"type": "Unary", "operand": {"type": "Literal", "value": 1},
+                "operator": {"type": "Bang", "lexeme": "-", "line": 1, "column": 1}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(error.message, "Token type Bang does not match lexeme '-'");
+    }
+}
@@ -1,28 +1,55 @@
 use crate::{
     expr::{Expr, ExprVisitor},
+    stmt::{Stmt, StmtVisitor},
     token::{Literal, Token},
 };
 
-// TODO: add multiline pretty-printing
-pub struct AstPrinter {}
+#[derive(Default)]
+pub struct AstPrinter {
+    round_trip: bool,
+    multiline: bool,
+}
 
 impl AstPrinter {
+    /// Print numbers the way they were written in the source, like 4.0,
+    /// rather than the way their value would be printed.
+    pub fn round_trip(mut self, round_trip: bool) -> Self {
+        self.round_trip = round_trip;
+        self
+    }
+
+    /// Put each operand of a node on a line of its own, indented below the
+    /// operator, unless they are all leaves. Collapsing the whitespace gives
+    /// the single-line form.
+    pub fn multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
     pub fn print(&mut self, expression: &Expr) -> String {
         expression.accept(self)
     }
 
     fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) -> String {
-        let mut result = String::new();
-
-        result += &format!("({} ", name);
-        result += &exprs
-            .iter()
-            .map(|e| e.accept(self))
-            .collect::<Vec<String>>()
-            .join(" ");
-        result += ")";
+        let parts = exprs.iter().map(|e| e.accept(self)).collect();
+        let nested = exprs.iter().any(|e| !is_leaf(e));
+        self.list(name, parts, nested)
+    }
 
-        result
+    /// Wrap already printed parts in parentheses after `name`. They go on
+    /// lines of their own if `nested`, meaning that some of them are nodes
+    /// rather than leaves.
+    fn list(&self, name: &str, parts: Vec<String>, nested: bool) -> String {
+        if self.multiline && nested {
+            let indented: Vec<String> = parts
+                .iter()
+                .flat_map(|part| part.lines())
+                .map(|line| format!("  {}", line))
+                .collect();
+            format!("({}\n{})", name, indented.join("\n"))
+        } else {
+            format!("({} {})", name, parts.join(" "))
+        }
     }
 }
 
@@ -44,28 +71,64 @@ impl ExprVisitor<String> for AstPrinter {
         self.parenthesize(&format!("{}{}", lho.lexeme, rho.lexeme), &[lhs, mhs, rhs])
     }
 
+    fn visit_logical(&mut self, lhs: &Expr, operator: &Token, rhs: &Expr) -> String {
+        self.parenthesize(&operator.lexeme, &[lhs, rhs])
+    }
+
+    fn visit_call(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> String {
+        let mut exprs = vec![callee];
+        exprs.extend(arguments);
+        self.parenthesize("call", &exprs)
+    }
+
+    fn visit_get(&mut self, object: &Expr, name: &Token) -> String {
+        let nested = !is_leaf(object);
+        let object = object.accept(self);
+        self.list(".", vec![object, name.lexeme.clone()], nested)
+    }
+
     fn visit_grouping(&mut self, expression: &Expr) -> String {
         self.parenthesize("group", &[expression])
     }
 
-    fn visit_literal(&mut self, value: &Literal) -> String {
-        match value {
-            Literal::String(s) => s.clone(),
-            Literal::Number(n) => n.to_string(),
-            Literal::Bool(value) => {
-                if *value {
-                    "true".into()
-                } else {
-                    "false".into()
-                }
-            }
-            Literal::Nil() => "nil".into(),
+    fn visit_literal(&mut self, value: &Literal, lexeme: &Option<String>) -> String {
+        match lexeme {
+            Some(lexeme) if self.round_trip => lexeme.clone(),
+            _ => value.to_string(),
         }
     }
 
     fn visit_unary(&mut self, operator: &Token, operand: &Expr) -> String {
         self.parenthesize(&operator.lexeme, &[operand])
     }
+
+    fn visit_variable(&mut self, name: &Token) -> String {
+        name.lexeme.clone()
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt], value: &Expr) -> String {
+        let mut parts: Vec<String> = statements.iter().map(|s| s.accept(self)).collect();
+        parts.push(value.accept(self));
+        // Statements always print as nodes
+        let nested = !statements.is_empty() || !is_leaf(value);
+        self.list("block", parts, nested)
+    }
+}
+
+/// Whether an expression prints without parentheses of its own
+fn is_leaf(expression: &Expr) -> bool {
+    matches!(expression, Expr::Literal { .. } | Expr::Variable { .. })
+}
+
+/// Statements only turn up inside block expressions
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_expression(&mut self, expression: &Expr) -> String {
+        self.parenthesize(";", &[expression])
+    }
+
+    fn visit_print(&mut self, expressions: &[Expr]) -> String {
+        self.parenthesize("print", &expressions.iter().collect::<Vec<_>>())
+    }
 }
 
 #[cfg(test)]
@@ -77,25 +140,30 @@ mod test {
     fn print_an_expression() {
         let expr = Expr::new_binary(
             Expr::new_unary(
-                Token::new(TokenType::Minus, "-", 0),
-                Expr::new_literal(Literal::Number(123.0)),
+                Token::new(TokenType::Minus, "-", 0, 0),
+                Expr::new_literal(Literal::Number(123.0), None),
             ),
-            Token::new(TokenType::Star, "*", 0),
-            Expr::new_grouping(Expr::new_literal(Literal::Number(45.67))),
+            Token::new(TokenType::Star, "*", 0, 0),
+            Expr::new_grouping(Expr::new_literal(Literal::Number(45.67), None)),
         );
 
-        assert_eq!(AstPrinter {}.print(&expr), "(* (- 123) (group 45.67))");
+        assert_eq!(
+            AstPrinter::default().print(&expr),
+            "(* (- 123) (group 45.67))"
+        );
     }
 
     #[test]
+    // The literals are arbitrary, not approximations of pi
+    #[allow(clippy::approx_constant)]
     fn ternary() {
         let expr = Expr::new_ternary(
-            Expr::new_literal(Literal::Bool(true)),
-            Token::new(TokenType::Interro, "?", 0),
-            Expr::new_literal(Literal::Number(3.14)),
-            Token::new(TokenType::Colon, ":", 0),
-            Expr::new_literal(Literal::Number(6.28)),
+            Expr::new_literal(Literal::Bool(true), None),
+            Token::new(TokenType::Interro, "?", 0, 0),
+            Expr::new_literal(Literal::Number(3.14), None),
+            Token::new(TokenType::Colon, ":", 0, 0),
+            Expr::new_literal(Literal::Number(6.28), None),
         );
-        assert_eq!(AstPrinter {}.print(&expr), "(?: true 3.14 6.28)");
+        assert_eq!(AstPrinter::default().print(&expr), "(?: true 3.14 6.28)");
     }
 }
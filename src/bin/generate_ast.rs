@@ -8,9 +8,24 @@ static EXPRESSION_GRAMMAR: &[&str] = &[
     // Yes. Having acknowledged that: how often do you get the chance to talk
     // about a middle-hand side and a left-hand operator?
     "Ternary  : lhs: Expr, lho: Token, mhs: Expr, rho: Token, rhs: Expr",
+    "Logical  : lhs: Expr, operator: Token, rhs: Expr",
+    "Call     : callee: Expr, paren: Token, arguments: Vec<Expr>",
+    "Get      : object: Expr, name: Token",
     "Grouping : expression: Expr",
-    "Literal  : value: Literal",
+    // The lexeme is only kept for numbers written differently from how they
+    // would be printed, like 4.0 or 007.
+    "Literal  : value: Literal, lexeme: Option<String>",
     "Unary    : operator: Token, operand: Expr",
+    "Variable : name: Token",
+    // Extension: statements followed by the expression whose value the block
+    // takes, as in `{ print 1; 2 }`
+    "Block    : statements: Vec<Stmt>, value: Expr",
+];
+
+static STATEMENT_GRAMMAR: &[&str] = &[
+    "Expression : expression: Expr",
+    // Extension: any number of values, separated by commas
+    "Print      : expressions: Vec<Expr>",
 ];
 
 struct Symbol {
@@ -18,6 +33,30 @@ struct Symbol {
     symbol_type: String,
 }
 
+impl Symbol {
+    /// Collections already live on the heap and options of them are small, so
+    /// only single values get boxed
+    fn is_boxed(&self) -> bool {
+        !(self.symbol_type.starts_with("Vec<") || self.symbol_type.starts_with("Option<"))
+    }
+
+    fn field_type(&self) -> String {
+        if self.is_boxed() {
+            format!("Box<{}>", self.symbol_type)
+        } else {
+            self.symbol_type.clone()
+        }
+    }
+
+    /// Type of the symbol as passed to a visitor
+    fn borrowed_type(&self) -> String {
+        match self.symbol_type.strip_prefix("Vec<") {
+            Some(element) => format!("[{}", element.replace('>', "]")),
+            None => self.symbol_type.clone(),
+        }
+    }
+}
+
 struct Rule {
     head: String,
     body: Vec<Symbol>,
@@ -51,30 +90,50 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE;
     }
 
-    let ast_path: PathBuf = [&args[1], "expr.rs"].iter().collect();
+    let expr_header = r#"// generated by: cargo run --bin generate_ast src
+
+use crate::stmt::Stmt;
+use crate::token::{Literal, Token};
+
+"#;
+    let stmt_header = r#"// generated by: cargo run --bin generate_ast src
+
+use crate::expr::Expr;
+
+"#;
+
+    if write_ast(&args[1], "Expr", expr_header, EXPRESSION_GRAMMAR)
+        && write_ast(&args[1], "Stmt", stmt_header, STATEMENT_GRAMMAR)
+    {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Write the enum, constructors and visitor trait for one grammar to
+/// `<dir>/<base_name in lowercase>.rs`, returning whether that worked.
+fn write_ast(dir: &str, base_name: &str, header: &str, grammar: &[&str]) -> bool {
+    let ast_path: PathBuf = [dir, &format!("{}.rs", base_name.to_ascii_lowercase())]
+        .iter()
+        .collect();
     let file = File::options()
         .write(true)
         .truncate(true)
         .create(true)
         .open(&ast_path);
 
-    let header = r#"// generated by: cargo run --bin generate_ast src
-
-use crate::token::{Literal, Token};
-
-"#;
-
-    let grammar = parse_grammar(EXPRESSION_GRAMMAR);
+    let grammar = parse_grammar(grammar);
     match file {
         Ok(mut file) => match write!(file, "{}", header)
-            .and_then(|_| define_ast(&mut file, &grammar))
-            .and_then(|_| define_impl(&mut file, &grammar))
-            .and_then(|_| define_visitor(&mut file, &grammar))
+            .and_then(|_| define_ast(&mut file, base_name, &grammar))
+            .and_then(|_| define_impl(&mut file, base_name, &grammar))
+            .and_then(|_| define_visitor(&mut file, base_name, &grammar))
         {
-            Ok(_) => ExitCode::SUCCESS,
+            Ok(_) => true,
             Err(error) => {
                 eprintln!("Failed to write to {}: {}", ast_path.display(), error);
-                ExitCode::FAILURE
+                false
             }
         },
 
@@ -84,19 +143,27 @@ use crate::token::{Literal, Token};
                 ast_path.display(),
                 error
             );
-            ExitCode::FAILURE
+            false
         }
     }
 }
 
-fn define_ast(out: &mut dyn Write, grammar: &[Rule]) -> Result<(), std::io::Error> {
+fn define_ast(
+    out: &mut dyn Write,
+    base_name: &str,
+    grammar: &[Rule],
+) -> Result<(), std::io::Error> {
     writeln!(out, "#[derive(Debug, PartialEq)]")?;
-    writeln!(out, "pub enum Expr {{")?;
+    writeln!(
+        out,
+        "#[cfg_attr(feature = \"serde\", derive(serde::Serialize, serde::Deserialize))]"
+    )?;
+    writeln!(out, "pub enum {} {{", base_name)?;
 
     for rule in grammar {
         writeln!(out, "    {} {{", rule.head)?;
         for symbol in &rule.body {
-            writeln!(out, "        {}: Box<{}>,", symbol.name, symbol.symbol_type)?;
+            writeln!(out, "        {}: {},", symbol.name, symbol.field_type())?;
         }
         writeln!(out, "    }},")?;
     }
@@ -105,17 +172,25 @@ fn define_ast(out: &mut dyn Write, grammar: &[Rule]) -> Result<(), std::io::Erro
     Ok(())
 }
 
-fn define_impl(out: &mut dyn Write, grammar: &[Rule]) -> Result<(), std::io::Error> {
-    writeln!(out, "impl Expr {{")?;
-    define_accepter(out, grammar)?;
+fn define_impl(
+    out: &mut dyn Write,
+    base_name: &str,
+    grammar: &[Rule],
+) -> Result<(), std::io::Error> {
+    writeln!(out, "impl {} {{", base_name)?;
+    define_accepter(out, base_name, grammar)?;
     for rule in grammar {
-        define_new(out, rule)?;
+        define_new(out, base_name, rule)?;
     }
     writeln!(out, "}}")?;
     Ok(())
 }
 
-fn define_accepter(out: &mut dyn Write, grammar: &[Rule]) -> Result<(), std::io::Error> {
+fn define_accepter(
+    out: &mut dyn Write,
+    base_name: &str,
+    grammar: &[Rule],
+) -> Result<(), std::io::Error> {
     // Not sure it makes a lot of sense to call this a visitor pattern - it
     // certainly isn't what Crafting Interpreters or Design Patterns describe,
     // and it doesn't match the Rust Design Patterns description either.
@@ -123,7 +198,8 @@ fn define_accepter(out: &mut dyn Write, grammar: &[Rule]) -> Result<(), std::io:
 
     writeln!(
         out,
-        "    pub fn accept<R>(&self, visitor: &mut dyn ExprVisitor<R>) -> R {{"
+        "    pub fn accept<R>(&self, visitor: &mut dyn {}Visitor<R>) -> R {{",
+        base_name
     )?;
     writeln!(out, "        match self {{")?;
 
@@ -137,7 +213,8 @@ fn define_accepter(out: &mut dyn Write, grammar: &[Rule]) -> Result<(), std::io:
 
         writeln!(
             out,
-            "            Expr::{} {{ {} }} => visitor.visit_{}({}),",
+            "            {}::{} {{ {} }} => visitor.visit_{}({}),",
+            base_name,
             rule.head,
             match_fields,
             rule.head.to_ascii_lowercase(),
@@ -150,24 +227,32 @@ fn define_accepter(out: &mut dyn Write, grammar: &[Rule]) -> Result<(), std::io:
     Ok(())
 }
 
-fn define_new(out: &mut dyn Write, rule: &Rule) -> Result<(), std::io::Error> {
+fn define_new(out: &mut dyn Write, base_name: &str, rule: &Rule) -> Result<(), std::io::Error> {
     writeln!(
         out,
-        "    pub fn new_{}({}) -> Expr {{",
+        "    pub fn new_{}({}) -> {} {{",
         rule.head.to_ascii_lowercase(),
         rule.body
             .iter()
             .map(|sym| format!("{}: {}", sym.name, sym.symbol_type))
             .collect::<Vec<String>>()
-            .join(", ")
+            .join(", "),
+        base_name
     )?;
     writeln!(
         out,
-        "        Expr::{} {{ {} }}",
+        "        {}::{} {{ {} }}",
+        base_name,
         rule.head,
         rule.body
             .iter()
-            .map(|sym| format!("{0}: Box::new({0})", sym.name))
+            .map(|sym| {
+                if sym.is_boxed() {
+                    format!("{0}: Box::new({0})", sym.name)
+                } else {
+                    sym.name.clone()
+                }
+            })
             .collect::<Vec<String>>()
             .join(", ")
     )?;
@@ -176,8 +261,12 @@ fn define_new(out: &mut dyn Write, rule: &Rule) -> Result<(), std::io::Error> {
     Ok(())
 }
 
-fn define_visitor(out: &mut dyn Write, grammar: &[Rule]) -> Result<(), std::io::Error> {
-    writeln!(out, "pub trait ExprVisitor<R> {{")?;
+fn define_visitor(
+    out: &mut dyn Write,
+    base_name: &str,
+    grammar: &[Rule],
+) -> Result<(), std::io::Error> {
+    writeln!(out, "pub trait {}Visitor<R> {{", base_name)?;
 
     for rule in grammar {
         write!(
@@ -186,7 +275,7 @@ fn define_visitor(out: &mut dyn Write, grammar: &[Rule]) -> Result<(), std::io::
             rule.head.to_ascii_lowercase()
         )?;
         for symbol in &rule.body {
-            write!(out, ", {}: &{}", symbol.name, symbol.symbol_type)?;
+            write!(out, ", {}: &{}", symbol.name, symbol.borrowed_type())?;
         }
         writeln!(out, ") -> R;")?;
     }
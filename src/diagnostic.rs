@@ -0,0 +1,216 @@
+//! Reporting of scanner and parser errors through a common trait, including
+//! machine-readable rendering for editors and other tooling that would rather
+//! not scrape the human-readable output.
+
+use std::ops::Range;
+
+use crate::scanner::source_line;
+
+/// Whether a diagnostic stops the program from running
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Common interface of errors and warnings that point at a place in the
+/// source, so that they can all be reported the same way.
+pub trait Diagnostic: std::fmt::Display {
+    /// Stable code identifying the kind of error
+    fn code(&self) -> &'static str;
+    fn line(&self) -> usize;
+    fn column(&self) -> usize;
+    fn message(&self) -> &str;
+
+    /// Columns on `line` that the error covers, for underlining. Defaults to
+    /// just the one at `column`.
+    fn span(&self) -> Range<usize> {
+        self.column()..self.column() + 1
+    }
+
+    /// Errors unless stated otherwise
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// Render the diagnostic for people, with the line it's on and the span
+    /// underlined, like
+    ///
+    /// ```text
+    /// error[E1001]: Unexpected end of file. Unterminated (Expr)
+    ///  --> 1:7
+    ///   |
+    /// 1 | (1 + 2
+    ///   |       ^
+    /// ```
+    ///
+    /// `source` has to be what was scanned, with columns counted at the
+    /// default tab width. The source lines are left out if the line isn't in
+    /// it.
+    fn render(&self, source: &str) -> String {
+        let mut result = format!(
+            "{}[{}]: {}\n --> {}:{}",
+            self.severity().as_str(),
+            self.code(),
+            self.message(),
+            self.line(),
+            self.column()
+        );
+        let Some(text) = source_line(source, self.line()) else {
+            return result;
+        };
+
+        let gutter = " ".repeat(self.line().to_string().len());
+        let span = self.span();
+        // Tabs before the span are kept, so that the underline lines up
+        // however wide the terminal shows them
+        let indent: String = text
+            .chars()
+            .chain(std::iter::repeat(' '))
+            .take(span.start.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline = "^".repeat(span.len().max(1));
+        result += &format!(
+            "\n{gutter} |\n{} | {}\n{gutter} | {}{}",
+            self.line(),
+            text,
+            indent,
+            underline
+        );
+        result
+    }
+
+    fn to_json(&self) -> String {
+        to_json(
+            self.line(),
+            self.column(),
+            self.code(),
+            self.message(),
+            self.severity(),
+        )
+    }
+}
+
+/// Render a single diagnostic as a one-line JSON object.
+pub fn to_json(
+    line: usize,
+    column: usize,
+    code: &str,
+    message: &str,
+    severity: Severity,
+) -> String {
+    format!(
+        r#"{{"line":{},"column":{},"code":"{}","message":"{}","severity":"{}"}}"#,
+        line,
+        column,
+        escape(code),
+        escape(message),
+        severity.as_str()
+    )
+}
+
+/// Escape a string for inclusion in a JSON string literal.
+pub(crate) fn escape(string: &str) -> String {
+    let mut result = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '"' => result += "\\\"",
+            '\\' => result += "\\\\",
+            '\n' => result += "\\n",
+            '\r' => result += "\\r",
+            '\t' => result += "\\t",
+            c if c.is_control() => result += &format!("\\u{:04x}", c as u32),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::Diagnostic;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    #[test]
+    fn parser_error_as_json() {
+        let tokens = Scanner::new("(1 + 2").scan_tokens().unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(
+            error.to_json(),
+            r#"{"line":1,"column":7,"code":"E1001","message":"Unexpected end of file. Unterminated (Expr)","severity":"error"}"#
+        );
+    }
+
+    #[test]
+    fn scanner_error_as_json() {
+        let errors = Scanner::new("1 +\n \"oops").scan_tokens().unwrap_err();
+        assert_eq!(
+            errors[0].to_json(),
+            r#"{"line":2,"column":2,"code":"E0101","message":"Unterminated string","severity":"error"}"#
+        );
+    }
+
+    fn render(diagnostic: &dyn Diagnostic) -> String {
+        format!(
+            "{}:{}: {} [{}]",
+            diagnostic.line(),
+            diagnostic.column(),
+            diagnostic.message(),
+            diagnostic.code()
+        )
+    }
+
+    #[test]
+    fn render_through_trait() {
+        let errors = Scanner::new("\n  @").scan_tokens().unwrap_err();
+        assert_eq!(render(&errors[0]), "2:3: Unexpected character @ [E0001]");
+
+        let tokens = Scanner::new("(1 + 2").scan_tokens().unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(
+            render(&error),
+            "1:7: Unexpected end of file. Unterminated (Expr) [E1001]"
+        );
+    }
+
+    #[test]
+    fn render_with_source() {
+        let source = "print 1;\nprint (1 +\t2;\n";
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let error = Parser::new(&tokens).parse_program().unwrap_err();
+        assert_eq!(
+            error.render(source),
+            "error[E1001]: Unexpected token ';'. Unterminated (Expr)\n\
+             \x20--> 2:13\n\
+             \x20 |\n\
+             2 | print (1 +\t2;\n\
+             \x20 |           \t ^"
+        );
+
+        // A span wider than one column is underlined in full
+        let source = "1 + @@";
+        let errors = Scanner::new(source).scan_tokens().unwrap_err();
+        assert!(errors[0].render(source).ends_with("1 | 1 + @@\n  |     ^"));
+
+        // Without the line there's nothing to show
+        assert_eq!(
+            errors[0].render(""),
+            "error[E0001]: Unexpected character @\n --> 1:5"
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(super::escape("a\"b\\c\nd\u{1}"), r#"a\"b\\c\nd\u0001"#);
+    }
+}
@@ -1,8 +1,10 @@
 // generated by: cargo run --bin generate_ast src
 
+use crate::stmt::Stmt;
 use crate::token::{Literal, Token};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
     Binary {
         lhs: Box<Expr>,
@@ -16,25 +18,52 @@ pub enum Expr {
         rho: Box<Token>,
         rhs: Box<Expr>,
     },
+    Logical {
+        lhs: Box<Expr>,
+        operator: Box<Token>,
+        rhs: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Box<Token>,
+        arguments: Vec<Expr>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Box<Token>,
+    },
     Grouping {
         expression: Box<Expr>,
     },
     Literal {
         value: Box<Literal>,
+        lexeme: Option<String>,
     },
     Unary {
         operator: Box<Token>,
         operand: Box<Expr>,
     },
+    Variable {
+        name: Box<Token>,
+    },
+    Block {
+        statements: Vec<Stmt>,
+        value: Box<Expr>,
+    },
 }
 impl Expr {
     pub fn accept<R>(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
         match self {
             Expr::Binary { lhs, operator, rhs } => visitor.visit_binary(lhs, operator, rhs),
             Expr::Ternary { lhs, lho, mhs, rho, rhs } => visitor.visit_ternary(lhs, lho, mhs, rho, rhs),
+            Expr::Logical { lhs, operator, rhs } => visitor.visit_logical(lhs, operator, rhs),
+            Expr::Call { callee, paren, arguments } => visitor.visit_call(callee, paren, arguments),
+            Expr::Get { object, name } => visitor.visit_get(object, name),
             Expr::Grouping { expression } => visitor.visit_grouping(expression),
-            Expr::Literal { value } => visitor.visit_literal(value),
+            Expr::Literal { value, lexeme } => visitor.visit_literal(value, lexeme),
             Expr::Unary { operator, operand } => visitor.visit_unary(operator, operand),
+            Expr::Variable { name } => visitor.visit_variable(name),
+            Expr::Block { statements, value } => visitor.visit_block(statements, value),
         }
     }
     pub fn new_binary(lhs: Expr, operator: Token, rhs: Expr) -> Expr {
@@ -43,20 +72,40 @@ impl Expr {
     pub fn new_ternary(lhs: Expr, lho: Token, mhs: Expr, rho: Token, rhs: Expr) -> Expr {
         Expr::Ternary { lhs: Box::new(lhs), lho: Box::new(lho), mhs: Box::new(mhs), rho: Box::new(rho), rhs: Box::new(rhs) }
     }
+    pub fn new_logical(lhs: Expr, operator: Token, rhs: Expr) -> Expr {
+        Expr::Logical { lhs: Box::new(lhs), operator: Box::new(operator), rhs: Box::new(rhs) }
+    }
+    pub fn new_call(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Expr {
+        Expr::Call { callee: Box::new(callee), paren: Box::new(paren), arguments }
+    }
+    pub fn new_get(object: Expr, name: Token) -> Expr {
+        Expr::Get { object: Box::new(object), name: Box::new(name) }
+    }
     pub fn new_grouping(expression: Expr) -> Expr {
         Expr::Grouping { expression: Box::new(expression) }
     }
-    pub fn new_literal(value: Literal) -> Expr {
-        Expr::Literal { value: Box::new(value) }
+    pub fn new_literal(value: Literal, lexeme: Option<String>) -> Expr {
+        Expr::Literal { value: Box::new(value), lexeme }
     }
     pub fn new_unary(operator: Token, operand: Expr) -> Expr {
         Expr::Unary { operator: Box::new(operator), operand: Box::new(operand) }
     }
+    pub fn new_variable(name: Token) -> Expr {
+        Expr::Variable { name: Box::new(name) }
+    }
+    pub fn new_block(statements: Vec<Stmt>, value: Expr) -> Expr {
+        Expr::Block { statements, value: Box::new(value) }
+    }
 }
 pub trait ExprVisitor<R> {
     fn visit_binary(&mut self, lhs: &Expr, operator: &Token, rhs: &Expr) -> R;
     fn visit_ternary(&mut self, lhs: &Expr, lho: &Token, mhs: &Expr, rho: &Token, rhs: &Expr) -> R;
+    fn visit_logical(&mut self, lhs: &Expr, operator: &Token, rhs: &Expr) -> R;
+    fn visit_call(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> R;
+    fn visit_get(&mut self, object: &Expr, name: &Token) -> R;
     fn visit_grouping(&mut self, expression: &Expr) -> R;
-    fn visit_literal(&mut self, value: &Literal) -> R;
+    fn visit_literal(&mut self, value: &Literal, lexeme: &Option<String>) -> R;
     fn visit_unary(&mut self, operator: &Token, operand: &Expr) -> R;
+    fn visit_variable(&mut self, name: &Token) -> R;
+    fn visit_block(&mut self, statements: &[Stmt], value: &Expr) -> R;
 }
@@ -0,0 +1,613 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    io::{stderr, stdout, Stderr, Stdout, Write},
+};
+
+use crate::{
+    expr::{Expr, ExprVisitor},
+    natives,
+    parser::Parser,
+    scanner::Scanner,
+    stmt::{Stmt, StmtVisitor},
+    token::{Literal, Token, TokenType},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Nil(),
+    Native(NativeFunction),
+}
+
+/// Signature of functions implemented in Rust. Errors are attributed to the
+/// closing parenthesis of the call.
+pub type NativeFn = fn(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError>;
+
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: NativeFn,
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+/// Natives are identified by their name, as there's only ever one of each.
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+/// The form in which values are printed
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            // Integral numbers are printed without a trailing .0
+            Value::Number(n) => write!(f, "{}", Literal::Number(*n)),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Nil() => write!(f, "nil"),
+            Value::Native(_) => write!(f, "<native fn>"),
+        }
+    }
+}
+
+impl Value {
+    /// Name of the value's type, as returned by the type() native
+    pub fn type_name(&self) -> &'static str {
+        // TODO: "class" and "instance", once those exist
+        match self {
+            Value::String(_) => "string",
+            Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Nil() => "nil",
+            Value::Native(_) => "function",
+        }
+    }
+
+    /// Whether the value counts as true in a condition. As in Ruby, only nil
+    /// and false don't.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil() | Value::Bool(false))
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::String(s) => Value::String(s.clone()),
+            Literal::Char(c) => Value::String(c.to_string()),
+            Literal::Number(n) => Value::Number(*n),
+            Literal::Bool(b) => Value::Bool(*b),
+            Literal::Nil() => Value::Nil(),
+        }
+    }
+}
+
+/// The error for an expression nested too deeply to evaluate, attributed to
+/// the first token in it. Groupings are skipped over without recursing, as
+/// there may be any number of them.
+fn too_deep(expression: &Expr, max_depth: usize) -> RuntimeError {
+    let token = expression
+        .descendants()
+        .filter(|expr| !matches!(expr, Expr::Grouping { .. } | Expr::Block { .. }))
+        .find_map(Expr::primary_token)
+        .cloned()
+        .unwrap_or_else(|| Token::new(TokenType::Eof, "", 0, 0));
+    RuntimeError {
+        token,
+        message: format!(
+            "Expression nested more than {} levels deep to evaluate.",
+            max_depth
+        ),
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("{}: {:?}: {message}", token.line, token.token_type)]
+pub struct RuntimeError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub(crate) fn new(token: &Token, message: &str) -> Self {
+        RuntimeError {
+            token: token.clone(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Tree-walking evaluator for expressions. Anything the program prints goes
+/// to `out` and runtime errors are reported to `err`, which are stdout and
+/// stderr unless the interpreter is created with other writers.
+pub struct Interpreter<O: Write = Stdout, E: Write = Stderr> {
+    out: O,
+    err: E,
+    globals: HashMap<String, Value>,
+    /// Significant digits numbers are printed with, if not all of them
+    precision: Option<usize>,
+    /// How many expressions are being evaluated inside each other
+    depth: usize,
+    max_depth: usize,
+}
+
+/// Default limit on how deeply expressions can nest while being evaluated,
+/// before the interpreter gives up instead of overflowing the stack. The
+/// parser's limit usually comes first, but long chains of operators like
+/// `1 + 1 + ...` and trees that didn't come from the parser can go deeper.
+pub const DEFAULT_MAX_DEPTH: usize = 400;
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::with_output(stdout(), stderr())
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl<O: Write, E: Write> Interpreter<O, E> {
+    pub fn with_output(out: O, err: E) -> Self {
+        let globals = natives::natives()
+            .into_iter()
+            .map(|native| (native.name.to_string(), Value::Native(native)))
+            .collect();
+        Interpreter {
+            out,
+            err,
+            globals,
+            precision: None,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Round the numbers that `print` writes to this many significant digits
+    /// (at least 1), rather than printing as many as it takes to tell them
+    /// apart from any other number.
+    pub fn precision(mut self, precision: Option<usize>) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Limit how deeply expressions can nest inside each other while being
+    /// evaluated. Going deeper is a runtime error.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Give the program a global variable before it runs, for passing in
+    /// configuration. This replaces any native of the same name.
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        self.globals.insert(name.to_string(), value);
+    }
+
+    pub fn evaluate(&mut self, expression: &Expr) -> Result<Value, RuntimeError> {
+        if self.depth >= self.max_depth {
+            return Err(too_deep(expression, self.max_depth));
+        }
+
+        self.depth += 1;
+        let result = expression.accept(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Scan, parse and evaluate a single expression with this interpreter, so
+    /// that whatever it did to the globals is kept for the next one.
+    pub fn eval_str(&mut self, source: &str) -> Result<Value, crate::Error> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        let expr = Parser::new(&tokens).parse()?;
+        Ok(self.evaluate(&expr)?)
+    }
+
+    pub fn execute(&mut self, statement: &Stmt) -> Result<(), RuntimeError> {
+        statement.accept(self)
+    }
+
+    /// Execute statements in order, stopping at the first runtime error. That
+    /// error is reported to `err` and also returned.
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        for statement in statements {
+            if let Err(error) = self.execute(statement) {
+                writeln!(self.err, "{}", error).expect("Failed to write diagnostics");
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// A value as `print` writes it
+    fn format(&self, value: &Value) -> String {
+        match (value, self.precision) {
+            (Value::Number(n), Some(precision)) if n.is_finite() => {
+                // Round in scientific notation, where the precision counts
+                // significant digits, then print the rounded number as usual
+                let rounded = format!("{:.*e}", precision.saturating_sub(1), n);
+                Value::Number(rounded.parse().expect("Rounded number should parse")).to_string()
+            }
+            _ => value.to_string(),
+        }
+    }
+}
+
+fn number_operand(operator: &Token, operand: &Value) -> Result<f64, RuntimeError> {
+    match operand {
+        Value::Number(n) => Ok(*n),
+        _ => Err(RuntimeError::new(operator, "Operand must be a number.")),
+    }
+}
+
+fn number_operands(operator: &Token, lhs: &Value, rhs: &Value) -> Result<(f64, f64), RuntimeError> {
+    match (lhs, rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => Ok((*lhs, *rhs)),
+        _ => Err(RuntimeError::new(operator, "Operands must be numbers.")),
+    }
+}
+
+/// Order two numbers or, as an extension to Lox, two strings, which compare
+/// lexicographically. Mixing the two is an error.
+fn compare(operator: &Token, lhs: &Value, rhs: &Value) -> Result<Option<Ordering>, RuntimeError> {
+    match (lhs, rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => Ok(lhs.partial_cmp(rhs)),
+        (Value::String(lhs), Value::String(rhs)) => Ok(Some(lhs.cmp(rhs))),
+        _ => Err(RuntimeError::new(
+            operator,
+            "Operands must be two numbers or two strings.",
+        )),
+    }
+}
+
+/// Operands of the bitwise operators, which work on numbers truncated to
+/// integers.
+fn integer_operands(
+    operator: &Token,
+    lhs: &Value,
+    rhs: &Value,
+) -> Result<(i64, i64), RuntimeError> {
+    let (lhs, rhs) = number_operands(operator, lhs, rhs)?;
+    Ok((lhs as i64, rhs as i64))
+}
+
+/// Shift by a whole number of bits, which has to be less than the width of the
+/// integers being shifted.
+fn shift(
+    operator: &Token,
+    lhs: &Value,
+    rhs: &Value,
+    shift: fn(i64, u32) -> Option<i64>,
+) -> Result<Value, RuntimeError> {
+    let (lhs, rhs) = integer_operands(operator, lhs, rhs)?;
+    u32::try_from(rhs)
+        .ok()
+        .and_then(|rhs| shift(lhs, rhs))
+        .map(|result| Value::Number(result as f64))
+        .ok_or_else(|| RuntimeError::new(operator, "Shift amount must be between 0 and 63."))
+}
+
+/// Rather than quietly producing infinity or NaN, dividing by zero is a
+/// runtime error.
+fn nonzero_divisor(operator: &Token, lhs: &Value, rhs: &Value) -> Result<(f64, f64), RuntimeError> {
+    match number_operands(operator, lhs, rhs)? {
+        (_, 0.0) => Err(RuntimeError::new(operator, "Division by zero.")),
+        operands => Ok(operands),
+    }
+}
+
+impl<O: Write, E: Write> ExprVisitor<Result<Value, RuntimeError>> for Interpreter<O, E> {
+    fn visit_binary(
+        &mut self,
+        lhs: &Expr,
+        operator: &Token,
+        rhs: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        // Both operands are always evaluated, left to right, before the
+        // operator is applied.
+        let lhs = self.evaluate(lhs)?;
+        let rhs = self.evaluate(rhs)?;
+
+        match operator.token_type {
+            // As in C, the left operand of a comma is only evaluated for its
+            // side effects and the right operand is the result.
+            TokenType::Comma => Ok(rhs),
+            TokenType::Minus => {
+                let (lhs, rhs) = number_operands(operator, &lhs, &rhs)?;
+                Ok(Value::Number(lhs - rhs))
+            }
+            TokenType::Slash => {
+                let (lhs, rhs) = nonzero_divisor(operator, &lhs, &rhs)?;
+                Ok(Value::Number(lhs / rhs))
+            }
+            TokenType::Star => {
+                let (lhs, rhs) = number_operands(operator, &lhs, &rhs)?;
+                Ok(Value::Number(lhs * rhs))
+            }
+            TokenType::Percent => {
+                let (lhs, rhs) = nonzero_divisor(operator, &lhs, &rhs)?;
+                Ok(Value::Number(lhs % rhs))
+            }
+            TokenType::Plus => match (lhs, rhs) {
+                (Value::Number(lhs), Value::Number(rhs)) => Ok(Value::Number(lhs + rhs)),
+                (Value::String(lhs), Value::String(rhs)) => Ok(Value::String(lhs + &rhs)),
+                _ => Err(RuntimeError::new(
+                    operator,
+                    "Operands must be two numbers or two strings.",
+                )),
+            },
+            TokenType::Greater => Ok(Value::Bool(matches!(
+                compare(operator, &lhs, &rhs)?,
+                Some(Ordering::Greater)
+            ))),
+            TokenType::GreaterEqual => Ok(Value::Bool(matches!(
+                compare(operator, &lhs, &rhs)?,
+                Some(Ordering::Greater | Ordering::Equal)
+            ))),
+            TokenType::Less => Ok(Value::Bool(matches!(
+                compare(operator, &lhs, &rhs)?,
+                Some(Ordering::Less)
+            ))),
+            TokenType::LessEqual => Ok(Value::Bool(matches!(
+                compare(operator, &lhs, &rhs)?,
+                Some(Ordering::Less | Ordering::Equal)
+            ))),
+            TokenType::EqualEqual => Ok(Value::Bool(lhs == rhs)),
+            TokenType::BangEqual => Ok(Value::Bool(lhs != rhs)),
+            TokenType::Ampersand => {
+                let (lhs, rhs) = integer_operands(operator, &lhs, &rhs)?;
+                Ok(Value::Number((lhs & rhs) as f64))
+            }
+            TokenType::Pipe => {
+                let (lhs, rhs) = integer_operands(operator, &lhs, &rhs)?;
+                Ok(Value::Number((lhs | rhs) as f64))
+            }
+            TokenType::Caret => {
+                let (lhs, rhs) = integer_operands(operator, &lhs, &rhs)?;
+                Ok(Value::Number((lhs ^ rhs) as f64))
+            }
+            TokenType::LessLess => shift(operator, &lhs, &rhs, i64::checked_shl),
+            TokenType::GreaterGreater => shift(operator, &lhs, &rhs, i64::checked_shr),
+            _ => Err(RuntimeError::new(operator, "Unknown binary operator.")),
+        }
+    }
+
+    fn visit_ternary(
+        &mut self,
+        lhs: &Expr,
+        _lho: &Token,
+        mhs: &Expr,
+        _rho: &Token,
+        rhs: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        // Only the selected branch is evaluated.
+        if self.evaluate(lhs)?.is_truthy() {
+            self.evaluate(mhs)
+        } else {
+            self.evaluate(rhs)
+        }
+    }
+
+    fn visit_logical(
+        &mut self,
+        lhs: &Expr,
+        operator: &Token,
+        rhs: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        let lhs = self.evaluate(lhs)?;
+
+        // The right operand is only evaluated if it's needed
+        match operator.token_type {
+            TokenType::InterroInterro => match lhs {
+                Value::Nil() => self.evaluate(rhs),
+                lhs => Ok(lhs),
+            },
+            _ => Err(RuntimeError::new(operator, "Unknown logical operator.")),
+        }
+    }
+
+    fn visit_call(
+        &mut self,
+        callee: &Expr,
+        paren: &Token,
+        arguments: &[Expr],
+    ) -> Result<Value, RuntimeError> {
+        let callee = self.evaluate(callee)?;
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<Value>, RuntimeError>>()?;
+
+        // TODO: functions and classes
+        match callee {
+            Value::Native(native) => {
+                if arguments.len() != native.arity {
+                    return Err(RuntimeError::new(
+                        paren,
+                        &format!(
+                            "Expected {} arguments but got {}.",
+                            native.arity,
+                            arguments.len()
+                        ),
+                    ));
+                }
+                (native.function)(paren, &arguments)
+            }
+            _ => Err(RuntimeError::new(
+                paren,
+                "Can only call functions and classes.",
+            )),
+        }
+    }
+
+    fn visit_get(&mut self, object: &Expr, name: &Token) -> Result<Value, RuntimeError> {
+        self.evaluate(object)?;
+
+        // TODO: instances
+        Err(RuntimeError::new(name, "Only instances have properties."))
+    }
+
+    fn visit_grouping(&mut self, expression: &Expr) -> Result<Value, RuntimeError> {
+        self.evaluate(expression)
+    }
+
+    fn visit_literal(
+        &mut self,
+        value: &Literal,
+        _lexeme: &Option<String>,
+    ) -> Result<Value, RuntimeError> {
+        Ok(value.into())
+    }
+
+    fn visit_unary(&mut self, operator: &Token, operand: &Expr) -> Result<Value, RuntimeError> {
+        let operand = self.evaluate(operand)?;
+
+        match operator.token_type {
+            TokenType::Minus => Ok(Value::Number(-number_operand(operator, &operand)?)),
+            TokenType::Bang => Ok(Value::Bool(!operand.is_truthy())),
+            _ => Err(RuntimeError::new(operator, "Unknown unary operator.")),
+        }
+    }
+
+    fn visit_variable(&mut self, name: &Token) -> Result<Value, RuntimeError> {
+        // TODO: variable declarations. Only natives and globals defined by the
+        // host exist for now.
+        match self.globals.get(&name.lexeme) {
+            Some(value) => Ok(value.clone()),
+            None => Err(RuntimeError::new(
+                name,
+                &format!("Undefined variable '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt], value: &Expr) -> Result<Value, RuntimeError> {
+        // TODO: give blocks a scope of their own once there are variables
+        for statement in statements {
+            self.execute(statement)?;
+        }
+        self.evaluate(value)
+    }
+}
+
+impl<O: Write, E: Write> StmtVisitor<Result<(), RuntimeError>> for Interpreter<O, E> {
+    fn visit_expression(&mut self, expression: &Expr) -> Result<(), RuntimeError> {
+        self.evaluate(expression)?;
+        Ok(())
+    }
+
+    fn visit_print(&mut self, expressions: &[Expr]) -> Result<(), RuntimeError> {
+        // Evaluate before writing anything, so a failing print prints nothing
+        let values = expressions
+            .iter()
+            .map(|expression| self.evaluate(expression))
+            .collect::<Result<Vec<Value>, RuntimeError>>()?
+            .iter()
+            .map(|value| self.format(value))
+            .collect::<Vec<String>>();
+        writeln!(self.out, "{}", values.join(" ")).expect("Failed to write output");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    #[test]
+    fn display_values() {
+        assert_eq!(Value::Number(5.0).to_string(), "5");
+        assert_eq!(Value::Number(-0.25).to_string(), "-0.25");
+        assert_eq!(
+            Value::String("verbatim \"text\"".into()).to_string(),
+            "verbatim \"text\""
+        );
+        assert_eq!(Value::Bool(true).to_string(), "true");
+        assert_eq!(Value::Nil().to_string(), "nil");
+    }
+
+    #[test]
+    fn truthiness() {
+        assert!(Value::Number(0.0).is_truthy());
+        assert!(Value::String("".into()).is_truthy());
+        assert!(Value::Bool(true).is_truthy());
+        assert!(!Value::Bool(false).is_truthy());
+        assert!(!Value::Nil().is_truthy());
+    }
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        Parser::new(&tokens).parse_program().unwrap()
+    }
+
+    #[test]
+    fn interpret_to_buffer() {
+        let mut out = Vec::<u8>::new();
+        Interpreter::with_output(&mut out, std::io::sink())
+            .interpret(&parse("print 1 + 2;"))
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "3\n");
+    }
+
+    #[test]
+    fn interpret_nothing_on_error() {
+        let mut out = Vec::<u8>::new();
+        let result =
+            Interpreter::with_output(&mut out, std::io::sink()).interpret(&parse("print 1 + nil;"));
+        assert!(result.is_err());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn interpret_separates_output_and_errors() {
+        let mut out = Vec::<u8>::new();
+        let mut err = Vec::<u8>::new();
+        let mut interpreter = Interpreter::with_output(&mut out, &mut err);
+        let result = interpreter.interpret(&parse(
+            "print \"one\" + \"two\";\nprint 1 +\n-\"one\";\nprint 3;",
+        ));
+        assert!(result.is_err());
+
+        assert_eq!(String::from_utf8(out).unwrap(), "onetwo\n");
+        assert_eq!(
+            String::from_utf8(err).unwrap(),
+            "3: Minus: Operand must be a number.\n"
+        );
+    }
+
+    #[test]
+    fn too_deeply_nested() {
+        let source = format!("{}1 + 2{}", "(".repeat(1000), ")".repeat(1000));
+        let tokens = Scanner::new(&source).scan_tokens().unwrap();
+        let expr = Parser::new(&tokens).max_depth(1000).parse().unwrap();
+
+        let mut interpreter = Interpreter::new().max_depth(50);
+        let error = interpreter.evaluate(&expr).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Expression nested more than 50 levels deep to evaluate."
+        );
+        assert_eq!(error.token.lexeme, "+");
+
+        // The interpreter is still good for expressions that fit
+        let tokens = Scanner::new(&format!("{}1", "1 + ".repeat(40)))
+            .scan_tokens()
+            .unwrap();
+        let expr = Parser::new(&tokens).parse().unwrap();
+        assert_eq!(interpreter.evaluate(&expr).unwrap(), Value::Number(41.0));
+    }
+}
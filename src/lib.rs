@@ -1,5 +1,228 @@
+pub mod ast_json;
 pub mod ast_printer;
+pub mod diagnostic;
 pub mod expr;
+pub mod interpreter;
+pub mod lint;
+pub mod metrics;
+pub mod natives;
+pub mod optimizer;
 pub mod parser;
+pub mod position;
+pub mod repl;
 pub mod scanner;
+pub mod stmt;
 pub mod token;
+pub mod walk;
+
+use std::time::{Duration, Instant};
+
+use diagnostic::Diagnostic;
+use expr::Expr;
+use interpreter::{Interpreter, RuntimeError, Value};
+use lint::Warning;
+use parser::{Parser, ParserError};
+use scanner::{Scanner, ScannerError};
+use stmt::Stmt;
+use token::Token;
+
+/// Anything that can stop a program from running, from scanning through
+/// evaluation.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))]
+    Scanner(Vec<ScannerError>),
+    #[error("{0}")]
+    Parser(ParserError),
+    #[error("{0}")]
+    Runtime(RuntimeError),
+    /// Warnings that were asked to be treated as errors
+    #[error("{}", .0.iter().map(|w| w.to_string()).collect::<Vec<_>>().join("\n"))]
+    Warnings(Vec<Warning>),
+}
+
+impl Error {
+    /// The scanner or parser errors or warnings this consists of. Runtime
+    /// errors aren't diagnostics and yield none.
+    pub fn diagnostics(&self) -> Vec<&dyn Diagnostic> {
+        match self {
+            Error::Scanner(errors) => errors.iter().map(|e| e as &dyn Diagnostic).collect(),
+            Error::Parser(error) => vec![error],
+            Error::Warnings(warnings) => warnings.iter().map(|w| w as &dyn Diagnostic).collect(),
+            Error::Runtime(_) => vec![],
+        }
+    }
+}
+
+impl From<Vec<ScannerError>> for Error {
+    fn from(errors: Vec<ScannerError>) -> Self {
+        Error::Scanner(errors)
+    }
+}
+
+impl From<ParserError> for Error {
+    fn from(error: ParserError) -> Self {
+        Error::Parser(error)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Self {
+        Error::Runtime(error)
+    }
+}
+
+/// Wall-clock time spent in each phase of a run. Phases that didn't get to
+/// start because an earlier one failed take no time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timings {
+    pub scan: Duration,
+    pub parse: Duration,
+    pub evaluate: Duration,
+}
+
+/// Scan, parse and evaluate an expression, returning its value.
+pub fn run(source: &str) -> Result<Value, Error> {
+    run_timed(source).0
+}
+
+/// Evaluate an expression like `run`, and also report how long each phase
+/// took.
+pub fn run_timed(source: &str) -> (Result<Value, Error>, Timings) {
+    run_phases(source, 1, parse_expression, evaluate)
+}
+
+/// Scan, parse and execute a program, printing its output to stdout.
+pub fn run_program(source: &str) -> Result<(), Error> {
+    run_program_timed(source).0
+}
+
+/// Execute a program like `run_program`, and also report how long each phase
+/// took.
+pub fn run_program_timed(source: &str) -> (Result<(), Error>, Timings) {
+    run_phases(source, 1, parse_program, execute)
+}
+
+/// Scan and parse a program without running it, returning its JSON
+/// representation.
+pub fn dump_ast_json(source: &str) -> Result<String, Error> {
+    let tokens = Scanner::new(source).scan_tokens()?;
+    let statements = Parser::new(&tokens).parse_program()?;
+    Ok(ast_json::AstJsonPrinter {}.print_program(&statements))
+}
+
+/// Execute a program like `run_program_timed`, handing the warnings about it
+/// to `warn` first. With `werror`, any warnings are an error instead and the
+/// program isn't run. The program is scanned and parsed only once for both.
+pub fn lint_and_run_program_timed(
+    source: &str,
+    werror: bool,
+    warn: impl FnOnce(Vec<Warning>),
+) -> (Result<(), Error>, Timings) {
+    let (statements, mut timings) = scan_and_parse(source, 1, parse_program);
+    let statements = match statements {
+        Ok(statements) => statements,
+        Err(error) => return (Err(error), timings),
+    };
+    match lint_statements(&statements, werror) {
+        Ok(warnings) => warn(warnings),
+        Err(error) => return (Err(error), timings),
+    }
+
+    let start = Instant::now();
+    let result = execute(statements);
+    timings.evaluate = start.elapsed();
+    (result.map_err(Error::from), timings)
+}
+
+/// Scan and parse a program without running it, to find out whether it has
+/// any errors short of runtime errors.
+pub fn check(source: &str) -> Result<(), Error> {
+    let tokens = Scanner::new(source).scan_tokens()?;
+    Parser::new(&tokens).parse_program()?;
+    Ok(())
+}
+
+/// Scan and parse a program without running it, and return the warnings
+/// about it. With `werror`, any warnings are an error instead.
+pub fn lint(source: &str, werror: bool) -> Result<Vec<Warning>, Error> {
+    let tokens = Scanner::new(source).scan_tokens()?;
+    let statements = Parser::new(&tokens).parse_program()?;
+    lint_statements(&statements, werror)
+}
+
+/// The warnings about a parsed program, or an error with them for `werror`
+fn lint_statements(statements: &[Stmt], werror: bool) -> Result<Vec<Warning>, Error> {
+    let warnings = lint::lint_program(statements);
+    if werror && !warnings.is_empty() {
+        Err(Error::Warnings(warnings))
+    } else {
+        Ok(warnings)
+    }
+}
+
+fn parse_expression(tokens: &[Token]) -> Result<Expr, ParserError> {
+    Parser::new(tokens).parse()
+}
+
+fn evaluate(expr: Expr) -> Result<Value, RuntimeError> {
+    Interpreter::new().evaluate(&expr)
+}
+
+fn parse_program(tokens: &[Token]) -> Result<Vec<Stmt>, ParserError> {
+    Parser::new(tokens).parse_program()
+}
+
+fn execute(statements: Vec<Stmt>) -> Result<(), RuntimeError> {
+    execute_with(&mut Interpreter::new(), statements)
+}
+
+/// Execute statements with an interpreter that outlives them, so that what
+/// they did to it is kept
+fn execute_with(interpreter: &mut Interpreter, statements: Vec<Stmt>) -> Result<(), RuntimeError> {
+    statements
+        .iter()
+        .try_for_each(|statement| interpreter.execute(statement))
+}
+
+/// Scan, parse and execute a source whose lines are numbered from
+/// `first_line`, timing each phase.
+fn run_phases<T, R>(
+    source: &str,
+    first_line: usize,
+    parse: fn(&[Token]) -> Result<T, ParserError>,
+    execute: impl FnOnce(T) -> Result<R, RuntimeError>,
+) -> (Result<R, Error>, Timings) {
+    let (parsed, mut timings) = scan_and_parse(source, first_line, parse);
+    let parsed = match parsed {
+        Ok(parsed) => parsed,
+        Err(error) => return (Err(error), timings),
+    };
+
+    let start = Instant::now();
+    let result = execute(parsed);
+    timings.evaluate = start.elapsed();
+    (result.map_err(Error::from), timings)
+}
+
+/// The scanning and parsing phases of `run_phases`
+fn scan_and_parse<T>(
+    source: &str,
+    first_line: usize,
+    parse: fn(&[Token]) -> Result<T, ParserError>,
+) -> (Result<T, Error>, Timings) {
+    let mut timings = Timings::default();
+
+    let start = Instant::now();
+    let tokens = Scanner::new(source).first_line(first_line).scan_tokens();
+    timings.scan = start.elapsed();
+    let tokens = match tokens {
+        Ok(tokens) => tokens,
+        Err(errors) => return (Err(errors.into()), timings),
+    };
+
+    let start = Instant::now();
+    let parsed = parse(&tokens);
+    timings.parse = start.elapsed();
+    (parsed.map_err(Error::from), timings)
+}
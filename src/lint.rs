@@ -0,0 +1,202 @@
+//! Warnings about code that is valid but probably not what was meant. These
+//! never stop a program from running.
+
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    expr::Expr,
+    interpreter::Value,
+    stmt::Stmt,
+    token::{Token, TokenType},
+};
+
+/// Stable codes for warnings, in the W0xxx range.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WarningKind {
+    /// W0001
+    MixedTernaryTypes,
+    /// W0002
+    ChainedComparison,
+}
+
+impl WarningKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            WarningKind::MixedTernaryTypes => "W0001",
+            WarningKind::ChainedComparison => "W0002",
+        }
+    }
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq)]
+#[error("{}: {}: {message}", kind.code(), token.line)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub token: Token,
+    pub message: String,
+}
+
+impl Diagnostic for Warning {
+    fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    fn line(&self) -> usize {
+        self.token.line
+    }
+
+    fn column(&self) -> usize {
+        self.token.column
+    }
+
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn span(&self) -> std::ops::Range<usize> {
+        self.token.columns()
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+/// Warnings for an expression and everything in it, in source order.
+pub fn lint(expression: &Expr) -> Vec<Warning> {
+    expression.descendants().filter_map(check).collect()
+}
+
+/// Warnings for every statement of a program, in source order.
+pub fn lint_program(statements: &[Stmt]) -> Vec<Warning> {
+    statements
+        .iter()
+        .flat_map(Stmt::expressions)
+        .flat_map(lint)
+        .collect()
+}
+
+/// The warning for this one expression, not counting what's in it
+fn check(expression: &Expr) -> Option<Warning> {
+    match expression {
+        Expr::Ternary { lho, mhs, rhs, .. } => mixed_ternary_types(lho, mhs, rhs),
+        Expr::Binary { lhs, operator, .. } => chained_comparison(lhs, operator),
+        _ => None,
+    }
+}
+
+/// Branches that are literals of different types, like `c ? 1 : "one"`. Nil
+/// is left out, as it commonly stands in for "nothing" next to any type.
+fn mixed_ternary_types(lho: &Token, mhs: &Expr, rhs: &Expr) -> Option<Warning> {
+    let (Expr::Literal { value: mhs, .. }, Expr::Literal { value: rhs, .. }) = (mhs, rhs) else {
+        return None;
+    };
+    let mhs = Value::from(mhs.as_ref()).type_name();
+    let rhs = Value::from(rhs.as_ref()).type_name();
+    if mhs == rhs || mhs == "nil" || rhs == "nil" {
+        return None;
+    }
+
+    Some(Warning {
+        kind: WarningKind::MixedTernaryTypes,
+        token: lho.clone(),
+        message: format!("Branches of '?' have different types, {} and {}", mhs, rhs),
+    })
+}
+
+fn is_comparison(operator: &Token) -> bool {
+    matches!(
+        operator.token_type,
+        TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual
+    )
+}
+
+/// Comparisons like `a < b < c`, which is `(a < b) < c` and so compares a
+/// bool to whatever `c` is. Writing the parentheses shows it's on purpose.
+fn chained_comparison(lhs: &Expr, operator: &Token) -> Option<Warning> {
+    let Expr::Binary {
+        operator: inner, ..
+    } = lhs
+    else {
+        return None;
+    };
+    if !is_comparison(operator) || !is_comparison(inner) {
+        return None;
+    }
+
+    Some(Warning {
+        kind: WarningKind::ChainedComparison,
+        token: operator.clone(),
+        message: format!(
+            "'{}' compares the bool result of '{}'; did you mean to join the comparisons with 'and'?",
+            operator.lexeme, inner.lexeme
+        ),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn warnings(source: &str) -> Vec<Warning> {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        lint(&Parser::new(&tokens).parse().unwrap())
+    }
+
+    #[test]
+    fn mixed_ternary_types() {
+        let warnings = warnings("c ? 1 : \"two\"");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code(), "W0001");
+        assert_eq!(warnings[0].column(), 3);
+        assert_eq!(
+            warnings[0].message,
+            "Branches of '?' have different types, number and string"
+        );
+        assert_eq!(warnings[0].severity(), Severity::Warning);
+
+        // Nested ones are found too
+        assert_eq!(self::warnings("f(a ? true : 0)").len(), 1);
+    }
+
+    #[test]
+    fn consistent_ternary_types() {
+        assert!(warnings("c ? 1 : 2").is_empty());
+        assert!(warnings("c ? \"a\" : \"b\"").is_empty());
+        assert!(warnings("c ? 1 : nil").is_empty());
+        // Only literals have a type known without running anything
+        assert!(warnings("c ? 1 : x").is_empty());
+        assert!(warnings("c ? 1 : (\"two\")").is_empty());
+    }
+
+    #[test]
+    fn chained_comparison() {
+        let warnings = warnings("1 < 2 < 3");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code(), "W0002");
+        assert_eq!(warnings[0].column(), 7);
+        assert_eq!(
+            warnings[0].message,
+            "'<' compares the bool result of '<'; did you mean to join the comparisons with 'and'?"
+        );
+
+        assert_eq!(self::warnings("a >= b > c <= d").len(), 2);
+        assert!(self::warnings("(1 < 2) < 3").is_empty());
+        assert!(self::warnings("1 < 2 == 2 < 3").is_empty());
+        assert!(self::warnings("1 + 2 < 3").is_empty());
+    }
+
+    #[test]
+    fn lint_statements() {
+        let tokens = Scanner::new("print 1;\nprint c ? nil : 1, c ? 1 : false;")
+            .scan_tokens()
+            .unwrap();
+        let warnings = lint_program(&Parser::new(&tokens).parse_program().unwrap());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line(), 2);
+        assert_eq!(
+            warnings[0].to_string(),
+            "W0001: 2: Branches of '?' have different types, number and bool"
+        );
+    }
+}
@@ -1,20 +1,18 @@
 #![allow(clippy::result_large_err)]
 
+use jlox::diagnostic::Diagnostic;
 use std::{
     env,
-    io::{stdin, Read, Write},
+    io::{stdin, Write},
     path::{Path, PathBuf},
+    process::ExitCode,
 };
 use thiserror::Error;
 
-use jlox::{ast_printer, parser, scanner};
-
 #[derive(Error, Debug)]
 enum ELoxError {
-    #[error("{0:?}")]
-    Scanner(Vec<scanner::ScannerError>),
-    #[error("{0:}")]
-    Parser(parser::ParserError),
+    #[error("{0}")]
+    Lox(jlox::Error),
     #[error(" Failed to read: {0}")]
     FileNotFound(std::io::Error),
 }
@@ -26,15 +24,9 @@ struct LoxError {
     error: ELoxError,
 }
 
-impl From<Vec<scanner::ScannerError>> for ELoxError {
-    fn from(error: Vec<scanner::ScannerError>) -> Self {
-        ELoxError::Scanner(error)
-    }
-}
-
-impl From<parser::ParserError> for ELoxError {
-    fn from(error: parser::ParserError) -> Self {
-        ELoxError::Parser(error)
+impl From<jlox::Error> for ELoxError {
+    fn from(error: jlox::Error) -> Self {
+        ELoxError::Lox(error)
     }
 }
 
@@ -44,23 +36,135 @@ impl From<std::io::Error> for ELoxError {
     }
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let result = match args.len() {
-        1 => run_prompt(),
-        2 => run_file(&args[1]),
-        _ => {
-            eprintln!("Usage: jlox [script]");
-            Ok(())
+impl LoxError {
+    /// Print the error to stderr, either as text or as one JSON object per
+    /// diagnostic.
+    fn report(&self, diagnostics_json: bool) {
+        match &self.error {
+            ELoxError::Lox(error) if diagnostics_json && !error.diagnostics().is_empty() => {
+                for diagnostic in error.diagnostics() {
+                    eprintln!("{}", diagnostic.to_json());
+                }
+            }
+            _ => eprintln!("{}", self),
+        }
+    }
+
+    /// Exit status for the error, following sysexits.h like the reference
+    /// implementation.
+    fn exit_code(&self) -> ExitCode {
+        match &self.error {
+            ELoxError::Lox(jlox::Error::Runtime(_)) => ExitCode::from(70),
+            ELoxError::Lox(_) => ExitCode::from(65),
+            ELoxError::FileNotFound(_) => ExitCode::from(74),
+        }
+    }
+}
+
+const FLAGS: &[&str] = &[
+    "--diagnostics-json",
+    "--time",
+    "--dump-ast-json",
+    "--check",
+    "--werror",
+    "--version",
+    "--help",
+];
+
+const USAGE: &str = "Usage: jlox [--diagnostics-json] [--time] [--werror] [script]
+       jlox [--diagnostics-json] --dump-ast-json script
+       jlox [--diagnostics-json] [--werror] --check script
+       jlox --version | --help";
+
+const HELP: &str = "
+Runs a script, or starts a prompt if there is none.
+
+Flags:
+    --diagnostics-json  Report errors as one JSON object per line
+    --time              Print how long each phase took to stderr
+    --dump-ast-json     Print the script's syntax tree as JSON instead of running it
+    --check             Only scan and parse the script, reporting any errors
+    --werror            Treat warnings about the script as errors
+    --version           Print the version and exit
+    --help              Print this help and exit
+
+At the prompt, `:mode value|typed|ast` chooses how expression values are shown.";
+
+/// What the command line asks jlox to do
+#[derive(Debug, PartialEq)]
+enum Command<'args> {
+    Help,
+    Version,
+    Prompt,
+    Run(&'args str),
+    DumpAstJson(&'args str),
+    Check(&'args str),
+    /// The arguments don't make sense together
+    Usage,
+}
+
+/// Work out the command from the arguments, not counting the program name.
+/// `--help` and `--version` win over anything else.
+fn command(args: &[String]) -> Command<'_> {
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+    let paths: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| !FLAGS.contains(arg))
+        .collect();
+
+    if flag("--help") {
+        return Command::Help;
+    }
+    if flag("--version") {
+        return Command::Version;
+    }
+    match (paths.as_slice(), flag("--dump-ast-json"), flag("--check")) {
+        ([], false, false) => Command::Prompt,
+        ([path], false, false) => Command::Run(path),
+        ([path], true, false) => Command::DumpAstJson(path),
+        ([path], false, true) => Command::Check(path),
+        _ => Command::Usage,
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let diagnostics_json = args.iter().any(|arg| arg == "--diagnostics-json");
+    let time = args.iter().any(|arg| arg == "--time");
+    let werror = args.iter().any(|arg| arg == "--werror");
+
+    let result = match command(&args) {
+        Command::Help => {
+            println!("{}\n{}", USAGE, HELP);
+            return ExitCode::SUCCESS;
+        }
+        Command::Version => {
+            println!("jlox {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Command::Prompt => run_prompt(diagnostics_json, time),
+        Command::Run(path) => run_file(path, time, werror, diagnostics_json),
+        Command::DumpAstJson(path) => dump_file(path),
+        Command::Check(path) => check_file(path, werror, diagnostics_json),
+        Command::Usage => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(64);
         }
     };
 
-    if let Err(error) = result {
-        eprintln!("{}", error)
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            error.report(diagnostics_json);
+            error.exit_code()
+        }
     }
 }
 
-fn run_prompt() -> Result<(), LoxError> {
+fn run_prompt(diagnostics_json: bool, time: bool) -> Result<(), LoxError> {
+    let mut repl = jlox::repl::Repl::new();
+
     loop {
         print!("> ");
         std::io::stdout().flush().unwrap();
@@ -69,10 +173,29 @@ fn run_prompt() -> Result<(), LoxError> {
 
         match stdin().read_line(&mut line) {
             Ok(0) => break,
-            Ok(_) => match run(path, &line) {
-                Ok(_) => continue,
-                Err(error) => eprintln!("{}", error),
-            },
+            Ok(_) => {
+                if let Some(result) = repl.meta_command(&line) {
+                    match result {
+                        Ok(response) => println!("{}", response),
+                        Err(error) => eprintln!("{}", error),
+                    }
+                    continue;
+                }
+
+                let (result, timings) = repl.show_timed(&line);
+                if time {
+                    print_timings(&timings);
+                }
+                match result {
+                    Ok(Some(value)) => println!("{}", value),
+                    Ok(None) => continue,
+                    Err(error) => LoxError {
+                        path: path.into(),
+                        error: error.into(),
+                    }
+                    .report(diagnostics_json),
+                }
+            }
             Err(error) => {
                 return Err(LoxError {
                     path: path.into(),
@@ -85,48 +208,131 @@ fn run_prompt() -> Result<(), LoxError> {
     Ok(())
 }
 
-fn run_file(path: &str) -> Result<(), LoxError> {
-    let path: std::path::PathBuf = path.into();
+fn read_file(path: &Path) -> Result<String, LoxError> {
+    std::fs::read_to_string(path).map_err(|error| LoxError {
+        path: path.into(),
+        error: error.into(),
+    })
+}
 
-    let mut source = String::new();
+fn run_file(path: &str, time: bool, werror: bool, diagnostics_json: bool) -> Result<(), LoxError> {
+    let path = Path::new(path);
+    let source = read_file(path)?;
+    let (result, timings) = jlox::lint_and_run_program_timed(&source, werror, |warnings| {
+        print_warnings(path, warnings, diagnostics_json)
+    });
 
-    match std::fs::File::open(&path) {
-        Ok(mut file) => match file.read_to_string(&mut source) {
-            Ok(_) => run(&path, &source),
-            Err(error) => Err(LoxError {
-                path,
-                error: error.into(),
-            }),
-        },
-        Err(error) => Err(LoxError {
-            path,
-            error: error.into(),
-        }),
+    if time {
+        print_timings(&timings);
     }
+
+    result.map_err(|error| LoxError {
+        path: path.into(),
+        error: error.into(),
+    })
 }
 
-fn run(path: &Path, source: &str) -> Result<(), LoxError> {
-    let mut scanner = scanner::Scanner::new(source);
+/// Print the warnings about a script to stderr. With `werror` they are
+/// returned as an error instead, as are any errors found along the way.
+fn lint_file(
+    path: &Path,
+    source: &str,
+    werror: bool,
+    diagnostics_json: bool,
+) -> Result<(), LoxError> {
+    let warnings = jlox::lint(source, werror).map_err(|error| LoxError {
+        path: path.into(),
+        error: error.into(),
+    })?;
+    print_warnings(path, warnings, diagnostics_json);
+    Ok(())
+}
 
-    match scanner.scan_tokens() {
-        Ok(tokens) => {
-            let mut parser = parser::Parser { tokens: &tokens };
-            match parser.parse() {
-                Ok(expr) => {
-                    // TODO: add non-mutable visitor trait
-                    let mut printer = ast_printer::AstPrinter {};
-                    println!("{}", printer.print(&expr));
-                    Ok(())
-                }
-                Err(err) => Err(LoxError {
-                    path: path.into(),
-                    error: err.into(),
-                }),
-            }
+/// Print warnings to stderr, as text or as one JSON object per line
+fn print_warnings(path: &Path, warnings: Vec<jlox::lint::Warning>, diagnostics_json: bool) {
+    for warning in warnings {
+        if diagnostics_json {
+            eprintln!("{}", warning.to_json());
+        } else {
+            eprintln!("{}:{}", path.display(), warning);
         }
-        Err(errors) => Err(LoxError {
+    }
+}
+
+/// Print a script's AST as JSON instead of running it
+fn dump_file(path: &str) -> Result<(), LoxError> {
+    let path = Path::new(path);
+    match jlox::dump_ast_json(&read_file(path)?) {
+        Ok(json) => {
+            println!("{}", json);
+            Ok(())
+        }
+        Err(error) => Err(LoxError {
             path: path.into(),
-            error: errors.into(),
+            error: error.into(),
         }),
     }
 }
+
+/// Scan and parse a script without running it, only reporting errors and
+/// warnings
+fn check_file(path: &str, werror: bool, diagnostics_json: bool) -> Result<(), LoxError> {
+    let path = Path::new(path);
+    lint_file(path, &read_file(path)?, werror, diagnostics_json)
+}
+
+fn print_timings(timings: &jlox::Timings) {
+    eprintln!("scan: {:?}", timings.scan);
+    eprintln!("parse: {:?}", timings.parse);
+    eprintln!("evaluate: {:?}", timings.evaluate);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn help_and_version() {
+        assert_eq!(command(&args(&["--help"])), Command::Help);
+        assert_eq!(command(&args(&["--version"])), Command::Version);
+        assert_eq!(
+            command(&args(&["--time", "--help", "a", "b"])),
+            Command::Help
+        );
+        assert_eq!(command(&args(&["--version", "--help"])), Command::Help);
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(command(&args(&[])), Command::Prompt);
+        assert_eq!(command(&args(&["--time"])), Command::Prompt);
+        assert_eq!(command(&args(&["a.lox"])), Command::Run("a.lox"));
+        assert_eq!(
+            command(&args(&["--diagnostics-json", "a.lox"])),
+            Command::Run("a.lox")
+        );
+        assert_eq!(
+            command(&args(&["--dump-ast-json", "a.lox"])),
+            Command::DumpAstJson("a.lox")
+        );
+        assert_eq!(
+            command(&args(&["a.lox", "--check"])),
+            Command::Check("a.lox")
+        );
+        assert_eq!(
+            command(&args(&["--werror", "a.lox"])),
+            Command::Run("a.lox")
+        );
+
+        assert_eq!(command(&args(&["a.lox", "b.lox"])), Command::Usage);
+        assert_eq!(command(&args(&["--check"])), Command::Usage);
+        assert_eq!(
+            command(&args(&["--check", "--dump-ast-json", "a.lox"])),
+            Command::Usage
+        );
+    }
+}
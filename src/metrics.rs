@@ -0,0 +1,303 @@
+use std::ops::Add;
+
+use crate::{
+    expr::{Expr, ExprVisitor},
+    stmt::Stmt,
+    token::{Literal, Token},
+};
+
+/// Number of nodes of each kind in an expression tree.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct NodeCounts {
+    pub binaries: usize,
+    pub ternaries: usize,
+    pub logicals: usize,
+    pub calls: usize,
+    pub gets: usize,
+    pub groupings: usize,
+    pub literals: usize,
+    pub unaries: usize,
+    pub variables: usize,
+    pub blocks: usize,
+}
+
+impl NodeCounts {
+    pub fn total(&self) -> usize {
+        self.binaries
+            + self.ternaries
+            + self.logicals
+            + self.calls
+            + self.gets
+            + self.groupings
+            + self.literals
+            + self.unaries
+            + self.variables
+            + self.blocks
+    }
+}
+
+impl Add for NodeCounts {
+    type Output = NodeCounts;
+
+    fn add(self, other: NodeCounts) -> NodeCounts {
+        NodeCounts {
+            binaries: self.binaries + other.binaries,
+            ternaries: self.ternaries + other.ternaries,
+            logicals: self.logicals + other.logicals,
+            calls: self.calls + other.calls,
+            gets: self.gets + other.gets,
+            groupings: self.groupings + other.groupings,
+            literals: self.literals + other.literals,
+            unaries: self.unaries + other.unaries,
+            variables: self.variables + other.variables,
+            blocks: self.blocks + other.blocks,
+        }
+    }
+}
+
+pub struct NodeCounter {}
+
+impl NodeCounter {
+    pub fn count(&mut self, expression: &Expr) -> NodeCounts {
+        expression.accept(self)
+    }
+}
+
+impl ExprVisitor<NodeCounts> for NodeCounter {
+    fn visit_binary(&mut self, lhs: &Expr, _operator: &Token, rhs: &Expr) -> NodeCounts {
+        let this = NodeCounts {
+            binaries: 1,
+            ..Default::default()
+        };
+        this + self.count(lhs) + self.count(rhs)
+    }
+
+    fn visit_ternary(
+        &mut self,
+        lhs: &Expr,
+        _lho: &Token,
+        mhs: &Expr,
+        _rho: &Token,
+        rhs: &Expr,
+    ) -> NodeCounts {
+        let this = NodeCounts {
+            ternaries: 1,
+            ..Default::default()
+        };
+        this + self.count(lhs) + self.count(mhs) + self.count(rhs)
+    }
+
+    fn visit_logical(&mut self, lhs: &Expr, _operator: &Token, rhs: &Expr) -> NodeCounts {
+        let this = NodeCounts {
+            logicals: 1,
+            ..Default::default()
+        };
+        this + self.count(lhs) + self.count(rhs)
+    }
+
+    fn visit_call(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> NodeCounts {
+        let this = NodeCounts {
+            calls: 1,
+            ..Default::default()
+        };
+        arguments
+            .iter()
+            .fold(this + self.count(callee), |counts, argument| {
+                counts + self.count(argument)
+            })
+    }
+
+    fn visit_get(&mut self, object: &Expr, _name: &Token) -> NodeCounts {
+        let this = NodeCounts {
+            gets: 1,
+            ..Default::default()
+        };
+        this + self.count(object)
+    }
+
+    fn visit_grouping(&mut self, expression: &Expr) -> NodeCounts {
+        let this = NodeCounts {
+            groupings: 1,
+            ..Default::default()
+        };
+        this + self.count(expression)
+    }
+
+    fn visit_literal(&mut self, _value: &Literal, _lexeme: &Option<String>) -> NodeCounts {
+        NodeCounts {
+            literals: 1,
+            ..Default::default()
+        }
+    }
+
+    fn visit_unary(&mut self, _operator: &Token, operand: &Expr) -> NodeCounts {
+        let this = NodeCounts {
+            unaries: 1,
+            ..Default::default()
+        };
+        this + self.count(operand)
+    }
+
+    fn visit_variable(&mut self, _name: &Token) -> NodeCounts {
+        NodeCounts {
+            variables: 1,
+            ..Default::default()
+        }
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt], value: &Expr) -> NodeCounts {
+        let this = NodeCounts {
+            blocks: 1,
+            ..Default::default()
+        };
+        statements
+            .iter()
+            .flat_map(Stmt::expressions)
+            .fold(this + self.count(value), |counts, expression| {
+                counts + self.count(expression)
+            })
+    }
+}
+
+/// Measures the height of an expression tree, where a leaf has height 1.
+pub struct HeightMeasurer {}
+
+impl HeightMeasurer {
+    pub fn height(&mut self, expression: &Expr) -> usize {
+        expression.accept(self)
+    }
+}
+
+impl ExprVisitor<usize> for HeightMeasurer {
+    fn visit_binary(&mut self, lhs: &Expr, _operator: &Token, rhs: &Expr) -> usize {
+        1 + self.height(lhs).max(self.height(rhs))
+    }
+
+    fn visit_ternary(
+        &mut self,
+        lhs: &Expr,
+        _lho: &Token,
+        mhs: &Expr,
+        _rho: &Token,
+        rhs: &Expr,
+    ) -> usize {
+        1 + self.height(lhs).max(self.height(mhs)).max(self.height(rhs))
+    }
+
+    fn visit_logical(&mut self, lhs: &Expr, _operator: &Token, rhs: &Expr) -> usize {
+        1 + self.height(lhs).max(self.height(rhs))
+    }
+
+    fn visit_call(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> usize {
+        let callee = self.height(callee);
+        1 + arguments
+            .iter()
+            .map(|argument| self.height(argument))
+            .fold(callee, usize::max)
+    }
+
+    fn visit_get(&mut self, object: &Expr, _name: &Token) -> usize {
+        1 + self.height(object)
+    }
+
+    fn visit_grouping(&mut self, expression: &Expr) -> usize {
+        1 + self.height(expression)
+    }
+
+    fn visit_literal(&mut self, _value: &Literal, _lexeme: &Option<String>) -> usize {
+        1
+    }
+
+    fn visit_unary(&mut self, _operator: &Token, operand: &Expr) -> usize {
+        1 + self.height(operand)
+    }
+
+    fn visit_variable(&mut self, _name: &Token) -> usize {
+        1
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt], value: &Expr) -> usize {
+        let value = self.height(value);
+        1 + statements
+            .iter()
+            .flat_map(Stmt::expressions)
+            .map(|expression| self.height(expression))
+            .fold(value, usize::max)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{optimizer::Optimizer, parser::Parser, scanner::Scanner};
+
+    fn parse(source: &str) -> Expr {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        Parser::new(&tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn count_nodes() {
+        let expr = parse("(2 + 3) * -4 ? \"yes\" : !\"no\"");
+        let counts = NodeCounter {}.count(&expr);
+        assert_eq!(
+            counts,
+            NodeCounts {
+                binaries: 2,
+                ternaries: 1,
+                groupings: 1,
+                literals: 5,
+                unaries: 2,
+                ..Default::default()
+            }
+        );
+        assert_eq!(counts.total(), 11);
+    }
+
+    #[test]
+    fn count_calls() {
+        assert_eq!(
+            NodeCounter {}.count(&parse("a.b(c, 1)")),
+            NodeCounts {
+                calls: 1,
+                gets: 1,
+                literals: 1,
+                variables: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn folding_reduces_count() {
+        let expr = parse("(2 + 3) * -4 ? \"yes\" : !\"no\"");
+        let optimized = Optimizer {}.optimize(&expr);
+        assert_eq!(
+            NodeCounter {}.count(&optimized),
+            NodeCounts {
+                literals: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn height_of_literal() {
+        assert_eq!(HeightMeasurer {}.height(&parse("1")), 1);
+    }
+
+    #[test]
+    fn height_of_left_associative_chain() {
+        let expr = parse("1, 2, 3, 4");
+        assert_eq!(HeightMeasurer {}.height(&expr), 4);
+
+        // The chain nests down the left spine, so the left operand is one
+        // shorter and the right operand is a leaf.
+        if let Expr::Binary { lhs, rhs, .. } = expr {
+            assert_eq!(HeightMeasurer {}.height(&lhs), 3);
+            assert_eq!(HeightMeasurer {}.height(&rhs), 1);
+        } else {
+            panic!("Expected a binary expression");
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{
+    interpreter::{NativeFunction, RuntimeError, Value},
+    token::Token,
+};
+
+/// Functions implemented in Rust that every interpreter starts out with.
+pub fn natives() -> Vec<NativeFunction> {
+    vec![
+        NativeFunction {
+            name: "len",
+            arity: 1,
+            function: len,
+        },
+        NativeFunction {
+            name: "substring",
+            arity: 3,
+            function: substring,
+        },
+        NativeFunction {
+            name: "number",
+            arity: 1,
+            function: number,
+        },
+        NativeFunction {
+            name: "string",
+            arity: 1,
+            function: string,
+        },
+        NativeFunction {
+            name: "type",
+            arity: 1,
+            function: type_of,
+        },
+        NativeFunction {
+            name: "assert",
+            arity: 2,
+            function: assert,
+        },
+    ]
+}
+
+fn string_argument<'a>(paren: &Token, argument: &'a Value) -> Result<&'a str, RuntimeError> {
+    match argument {
+        Value::String(s) => Ok(s),
+        _ => Err(RuntimeError::new(paren, "Argument must be a string.")),
+    }
+}
+
+fn index_argument(paren: &Token, argument: &Value) -> Result<usize, RuntimeError> {
+    match argument {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        _ => Err(RuntimeError::new(
+            paren,
+            "Index must be a non-negative whole number.",
+        )),
+    }
+}
+
+/// len(s): the number of characters in a string
+fn len(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string_argument(paren, &arguments[0])?;
+    Ok(Value::Number(s.chars().count() as f64))
+}
+
+/// substring(s, start, end): the characters of s from start up to but not
+/// including end
+fn substring(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string_argument(paren, &arguments[0])?;
+    let start = index_argument(paren, &arguments[1])?;
+    let end = index_argument(paren, &arguments[2])?;
+
+    let length = s.chars().count();
+    if start > end || end > length {
+        return Err(RuntimeError::new(
+            paren,
+            &format!(
+                "Substring range {}..{} is out of bounds for a string of length {}.",
+                start, end, length
+            ),
+        ));
+    }
+
+    Ok(Value::String(
+        s.chars().skip(start).take(end - start).collect(),
+    ))
+}
+
+/// number(s): the number written in a string, ignoring surrounding whitespace
+fn number(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string_argument(paren, &arguments[0])?;
+    match s.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(Value::Number(n)),
+        _ => Err(RuntimeError::new(
+            paren,
+            &format!("Can't convert '{}' to a number.", s),
+        )),
+    }
+}
+
+/// string(v): any value as it would be printed
+fn string(_paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::String(arguments[0].to_string()))
+}
+
+/// type(v): the name of the value's type
+fn type_of(_paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::String(arguments[0].type_name().into()))
+}
+
+/// assert(cond, message): nil if cond is truthy, and a runtime error with the
+/// message otherwise
+fn assert(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    if arguments[0].is_truthy() {
+        Ok(Value::Nil())
+    } else {
+        Err(RuntimeError::new(paren, &arguments[1].to_string()))
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{
+    expr::{Expr, ExprVisitor},
+    interpreter::{Interpreter, Value},
+    stmt::{Stmt, StmtVisitor},
+    token::{Literal, Token, TokenType},
+};
+
+/// Constant folding pass: rewrites sub-expressions whose operands are all
+/// literals into the literal they evaluate to.
+pub struct Optimizer {}
+
+impl Optimizer {
+    pub fn optimize(&mut self, expression: &Expr) -> Expr {
+        expression.accept(self)
+    }
+}
+
+fn is_literal(expression: &Expr) -> bool {
+    matches!(expression, Expr::Literal { .. })
+}
+
+/// Evaluate an expression whose operands are all literals. Anything that fails
+/// to evaluate is left alone, so that the error still happens at runtime.
+fn fold(expression: Expr) -> Expr {
+    // Folding only evaluates, so nothing should ever be printed
+    let mut interpreter = Interpreter::with_output(std::io::sink(), std::io::sink());
+    let literal = match interpreter.evaluate(&expression) {
+        Ok(Value::String(s)) => Literal::String(s),
+        Ok(Value::Number(n)) => Literal::Number(n),
+        Ok(Value::Bool(b)) => Literal::Bool(b),
+        Ok(Value::Nil()) => Literal::Nil(),
+        Ok(Value::Native(_)) | Err(_) => return expression,
+    };
+    Expr::new_literal(literal, None)
+}
+
+impl ExprVisitor<Expr> for Optimizer {
+    fn visit_binary(&mut self, lhs: &Expr, operator: &Token, rhs: &Expr) -> Expr {
+        let lhs = self.optimize(lhs);
+        let rhs = self.optimize(rhs);
+
+        // A literal on the left of a comma has no side effects to keep around
+        if operator.token_type == TokenType::Comma && is_literal(&lhs) {
+            return rhs;
+        }
+
+        let fold_this = is_literal(&lhs) && is_literal(&rhs);
+        let expression = Expr::new_binary(lhs, operator.clone(), rhs);
+        if fold_this {
+            fold(expression)
+        } else {
+            expression
+        }
+    }
+
+    fn visit_ternary(
+        &mut self,
+        lhs: &Expr,
+        lho: &Token,
+        mhs: &Expr,
+        rho: &Token,
+        rhs: &Expr,
+    ) -> Expr {
+        let lhs = self.optimize(lhs);
+        let mhs = self.optimize(mhs);
+        let rhs = self.optimize(rhs);
+
+        if let Expr::Literal { value, .. } = &lhs {
+            return if Value::from(value.as_ref()).is_truthy() {
+                mhs
+            } else {
+                rhs
+            };
+        }
+
+        Expr::new_ternary(lhs, lho.clone(), mhs, rho.clone(), rhs)
+    }
+
+    fn visit_logical(&mut self, lhs: &Expr, operator: &Token, rhs: &Expr) -> Expr {
+        let lhs = self.optimize(lhs);
+        let rhs = self.optimize(rhs);
+
+        if let Expr::Literal { value, .. } = &lhs {
+            if operator.token_type == TokenType::InterroInterro {
+                return if matches!(**value, Literal::Nil()) {
+                    rhs
+                } else {
+                    lhs
+                };
+            }
+        }
+
+        Expr::new_logical(lhs, operator.clone(), rhs)
+    }
+
+    fn visit_call(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> Expr {
+        Expr::new_call(
+            self.optimize(callee),
+            paren.clone(),
+            arguments.iter().map(|a| self.optimize(a)).collect(),
+        )
+    }
+
+    fn visit_get(&mut self, object: &Expr, name: &Token) -> Expr {
+        Expr::new_get(self.optimize(object), name.clone())
+    }
+
+    fn visit_grouping(&mut self, expression: &Expr) -> Expr {
+        let expression = self.optimize(expression);
+        if is_literal(&expression) {
+            expression
+        } else {
+            Expr::new_grouping(expression)
+        }
+    }
+
+    fn visit_literal(&mut self, value: &Literal, lexeme: &Option<String>) -> Expr {
+        Expr::new_literal(value.clone(), lexeme.clone())
+    }
+
+    fn visit_unary(&mut self, operator: &Token, operand: &Expr) -> Expr {
+        let operand = self.optimize(operand);
+        let fold_this = is_literal(&operand);
+        let expression = Expr::new_unary(operator.clone(), operand);
+        if fold_this {
+            fold(expression)
+        } else {
+            expression
+        }
+    }
+
+    fn visit_variable(&mut self, name: &Token) -> Expr {
+        Expr::new_variable(name.clone())
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt], value: &Expr) -> Expr {
+        let value = self.optimize(value);
+        if statements.is_empty() {
+            return value;
+        }
+        Expr::new_block(statements.iter().map(|s| s.accept(self)).collect(), value)
+    }
+}
+
+/// Statements only turn up inside block expressions
+impl StmtVisitor<Stmt> for Optimizer {
+    fn visit_expression(&mut self, expression: &Expr) -> Stmt {
+        Stmt::new_expression(self.optimize(expression))
+    }
+
+    fn visit_print(&mut self, expressions: &[Expr]) -> Stmt {
+        Stmt::new_print(expressions.iter().map(|e| self.optimize(e)).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ast_printer::AstPrinter, parser::Parser, scanner::Scanner};
+
+    fn optimize(source: &str) -> String {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let expr = Parser::new(&tokens).parse().unwrap();
+        AstPrinter::default().print(&Optimizer {}.optimize(&expr))
+    }
+
+    #[test]
+    fn fold_arithmetic() {
+        assert_eq!(optimize("2 + 3 * 4"), "14");
+        assert_eq!(optimize("(2 + 3) * 4"), "20");
+        assert_eq!(optimize("-(1 - 3)"), "2");
+        assert_eq!(optimize("1 < 2 == true"), "true");
+    }
+
+    #[test]
+    fn fold_unary_and_strings() {
+        assert_eq!(optimize("!true"), "false");
+        assert_eq!(optimize("\"con\" + \"cat\""), "concat");
+    }
+
+    #[test]
+    fn fold_comma_and_ternary() {
+        assert_eq!(optimize("1, 2"), "2");
+        assert_eq!(optimize("nil ? 1 : 2 + 2"), "4");
+        assert_eq!(optimize("f(1 + 2).x"), "(. (call f 3) x)");
+        assert_eq!(optimize("nil ?? 1 + 1"), "2");
+        assert_eq!(optimize("0 ?? f()"), "0");
+        assert_eq!(optimize("a ?? 1 + 1"), "(?? a 2)");
+    }
+
+    #[test]
+    fn leave_runtime_errors() {
+        assert_eq!(optimize("1 + \"one\""), "(+ 1 one)");
+        assert_eq!(optimize("1 / (1 - 1)"), "(/ 1 0)");
+        assert_eq!(optimize("(1 + 2) + -\"one\""), "(+ 3 (- one))");
+        assert_eq!(optimize("(-\"one\", 2)"), "(group (, (- one) 2))");
+    }
+}
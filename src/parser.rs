@@ -1,64 +1,277 @@
 use crate::{
+    diagnostic::Diagnostic,
     expr::Expr,
-    token::{Token, TokenType},
+    stmt::Stmt,
+    token::{Associativity, Literal, Token, TokenType},
 };
 
-use std::result::Result;
+use std::{iter::Cloned, ops::Range, result::Result, slice::Iter};
+
+/// Calls can't have more arguments than this, as in the reference
+/// implementation.
+const MAX_ARGUMENTS: usize = 255;
+
+/// Default limit on how deeply expressions can nest before the parser gives up
+/// instead of overflowing the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 100;
 
 /// A recursive descent parser that walks through the available tokens one at a
-/// time, eventually producing an Expr or ParserError.
-pub struct Parser<'tokens> {
-    pub tokens: &'tokens [Token],
+/// time, eventually producing an Expr or ParserError. Tokens can come from any
+/// iterator, of which the parser only ever looks one token ahead.
+pub struct Parser<I: Iterator<Item = Token>> {
+    tokens: I,
+    /// The next token to be consumed, taken from `tokens` ahead of time
+    current: Option<Token>,
+    /// Errors that don't stop the parser from carrying on
+    errors: Vec<ParserError>,
+    /// Current nesting depth
+    depth: usize,
+    /// Already parsed group to use as the next primary expression, so that
+    /// `group` can carry on with the expression around it
+    seed: Option<Expr>,
+    max_depth: usize,
+    /// Parse expressions by precedence climbing instead of recursive descent
+    pratt: bool,
+    /// Accept `{ statements; expression }` as an expression
+    block_expressions: bool,
+}
+
+/// Stable diagnostic codes for parser errors, in the E1xxx range.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParserErrorKind {
+    /// E1001
+    MissingRightParen,
+    /// E1002
+    ExpectedExpression,
+    /// E1003
+    MissingColon,
+    /// E1004
+    MissingLeftOperand,
+    /// E1005
+    ExpectedPropertyName,
+    /// E1006
+    TooManyArguments,
+    /// E1007
+    TooDeeplyNested,
+    /// E1008
+    MissingSemicolon,
+    /// E1009
+    ExpectedEndOfFile,
+    /// E1010
+    MissingRightBrace,
+}
+
+impl ParserErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserErrorKind::MissingRightParen => "E1001",
+            ParserErrorKind::ExpectedExpression => "E1002",
+            ParserErrorKind::MissingColon => "E1003",
+            ParserErrorKind::MissingLeftOperand => "E1004",
+            ParserErrorKind::ExpectedPropertyName => "E1005",
+            ParserErrorKind::TooManyArguments => "E1006",
+            ParserErrorKind::TooDeeplyNested => "E1007",
+            ParserErrorKind::MissingSemicolon => "E1008",
+            ParserErrorKind::ExpectedEndOfFile => "E1009",
+            ParserErrorKind::MissingRightBrace => "E1010",
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
-#[error("{}: {:?}: {message}", token.line, token.token_type)]
+#[error("{}: {}: {:?}: {message}", kind.code(), token.line, token.token_type)]
 pub struct ParserError {
+    pub kind: ParserErrorKind,
     pub token: Token,
     pub message: String,
 }
 
-impl<'tokens> Parser<'tokens> {
+impl Diagnostic for ParserError {
+    fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    fn line(&self) -> usize {
+        self.token.line
+    }
+
+    fn column(&self) -> usize {
+        self.token.column
+    }
+
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The whole of the offending token
+    fn span(&self) -> Range<usize> {
+        self.token.columns()
+    }
+}
+
+/// Wrap the error that kept the operand to the left of a binary operator from
+/// being parsed.
+fn missing_left_operand(operator: Token, err: ParserError) -> ParserError {
+    ParserError {
+        message: format!(
+            "Failed to parse left-hand operator for '{}': {}",
+            operator.lexeme, err
+        ),
+        kind: ParserErrorKind::MissingLeftOperand,
+        token: operator,
+    }
+}
+
+impl<'tokens> Parser<Cloned<Iter<'tokens, Token>>> {
+    /// Parse a slice of tokens ending in Eof, like the scanner produces. Debug
+    /// builds panic on anything else, as that is a broken fixture rather than
+    /// a broken program.
+    pub fn new(tokens: &'tokens [Token]) -> Self {
+        debug_assert!(
+            matches!(tokens.last(), Some(token) if token.token_type == TokenType::Eof),
+            "Parser::new needs tokens that end in Eof"
+        );
+        Parser::from_tokens(tokens.iter().cloned())
+    }
+}
+
+impl<I: Iterator<Item = Token>> Parser<I> {
+    /// Parse tokens as they are produced, rather than from a slice
+    pub fn from_tokens(mut tokens: I) -> Self {
+        Parser {
+            current: tokens.next(),
+            tokens,
+            errors: Vec::new(),
+            depth: 0,
+            seed: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            pratt: false,
+            block_expressions: false,
+        }
+    }
+
+    /// Limit how deeply groupings, unary operators, ternaries, call arguments
+    /// and block expressions can nest inside each other. Groups directly
+    /// inside each other are parsed without recursing, but each still counts
+    /// as a level, as the passes after parsing do recurse into them.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Parse binary, ternary and `??` expressions with a Pratt parser driven
+    /// by `TokenType::precedence` rather than the recursive descent rules.
+    /// Both produce the same trees and errors.
+    pub fn pratt(mut self, pratt: bool) -> Self {
+        self.pratt = pratt;
+        self
+    }
+
+    /// Accept blocks of statements that end in an expression, like
+    /// `{ print 1; 2 }`, wherever an expression can go. The block takes the
+    /// value of that last expression. This is off by default so that braces
+    /// stay free for plain blocks.
+    pub fn block_expressions(mut self, block_expressions: bool) -> Self {
+        self.block_expressions = block_expressions;
+        self
+    }
+
+    /// Parse an expression that makes up the entire source. If the parser
+    /// reported any errors along the way, the first of those is returned even
+    /// if it managed to carry on.
     pub fn parse(&mut self) -> Result<Expr, ParserError> {
-        self.expression()
+        let expr = self.parse_expression()?;
+        if !self.is_at_end() {
+            self.consume(
+                TokenType::Eof,
+                ParserErrorKind::ExpectedEndOfFile,
+                "Expected end of file after expression",
+            )?;
+        }
+        Ok(expr)
+    }
+
+    /// Parse a single expression, leaving any tokens after it for the caller.
+    /// Errors are reported as for `parse`.
+    pub fn parse_expression(&mut self) -> Result<Expr, ParserError> {
+        let expr = self.expression()?;
+        self.check_errors(expr)
+    }
+
+    /// Parse statements up to the end of the file. Errors are reported as for
+    /// `parse`.
+    pub fn parse_program(&mut self) -> Result<Vec<Stmt>, ParserError> {
+        // program -> statement* EOF
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            statements.push(self.statement()?);
+        }
+        self.check_errors(statements)
+    }
+
+    /// Pass on a successful result unless errors were reported along the way,
+    /// in which case the first of those is returned instead.
+    fn check_errors<T>(&mut self, result: T) -> Result<T, ParserError> {
+        let mut errors = std::mem::take(&mut self.errors);
+        if errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(errors.remove(0))
+        }
+    }
+
+    /// The next token to be parsed, if any
+    pub fn current(&self) -> Option<&Token> {
+        self.current.as_ref()
+    }
+
+    /// Whether all tokens up to the end of the file have been parsed
+    pub fn is_at_end(&self) -> bool {
+        !matches!(self.current(), Some(token) if token.token_type != TokenType::Eof)
     }
 
     /// Return the next token, if any
     fn advance(&mut self) -> Option<Token> {
-        let result = self.tokens.first();
-        self.tokens = &self.tokens[1..];
-        result.cloned()
+        std::mem::replace(&mut self.current, self.tokens.next())
     }
 
     fn peek(&self) -> Option<Token> {
-        self.tokens.first().cloned()
+        self.current.clone()
+    }
+
+    /// Whether the next token is of the given type
+    fn check(&self, token_type: TokenType) -> bool {
+        matches!(self.current(), Some(token) if token.token_type == token_type)
     }
 
     /// Return the next token iff it matches one of the provided token types.
     fn match_one_of(&mut self, token_types: &[TokenType]) -> Option<Token> {
-        for token_type in token_types {
-            if let Some(token) = self.tokens.first() {
-                if token.token_type == *token_type {
-                    return self.advance();
-                }
-            }
+        match self.current() {
+            Some(token) if token_types.contains(&token.token_type) => self.advance(),
+            _ => None,
         }
-        None
     }
 
-    /// Return a token of the specified type or an error with the specified
-    /// message.
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, ParserError> {
+    /// Return a token of the specified type or an error of the specified kind
+    /// with the specified message.
+    fn consume(
+        &mut self,
+        token_type: TokenType,
+        kind: ParserErrorKind,
+        message: &str,
+    ) -> Result<Token, ParserError> {
         match self.peek() {
             Some(token) if token.token_type == token_type => Ok(self.advance().unwrap()),
             Some(token) => match token.token_type {
                 TokenType::Eof => Err(ParserError {
+                    kind,
                     token,
                     message: format!("Unexpected end of file. {}", message),
                 }),
                 _ => {
                     let lexeme: String = token.lexeme.clone();
                     Err(ParserError {
+                        kind,
                         token,
                         message: format!("Unexpected token '{}'. {}", lexeme, message),
                     })
@@ -68,44 +281,222 @@ impl<'tokens> Parser<'tokens> {
         }
     }
 
+    /// Run a recursive parsing step one level deeper, or fail if that would
+    /// exceed the maximum depth.
+    fn nested(
+        &mut self,
+        step: &dyn Fn(&mut Self) -> Result<Expr, ParserError>,
+    ) -> Result<Expr, ParserError> {
+        self.descend()?;
+        let result = step(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Go one level deeper, or fail if that would exceed the maximum depth.
+    /// Whoever goes deeper comes back up again.
+    fn descend(&mut self) -> Result<(), ParserError> {
+        if self.depth >= self.max_depth {
+            return Err(ParserError {
+                kind: ParserErrorKind::TooDeeplyNested,
+                token: self.peek().expect("Unexpected end of token stream"),
+                message: format!("Expression nested more than {} levels deep", self.max_depth),
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParserError> {
+        // statement -> expression_statement | print_statement
+        if self.match_one_of(&[TokenType::Print]).is_some() {
+            self.print_statement()
+        } else {
+            self.expression_statement()
+        }
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParserError> {
+        // print_statement -> "print" conditional_expression ( "," conditional_expression )* ";"
+        // As with call arguments, the commas separate values rather than
+        // being the comma operator.
+        let mut values = vec![self.argument()?];
+        while self.match_one_of(&[TokenType::Comma]).is_some() {
+            values.push(self.argument()?);
+        }
+        self.consume(
+            TokenType::Semicolon,
+            ParserErrorKind::MissingSemicolon,
+            "Expected ';' after value",
+        )?;
+        Ok(Stmt::new_print(values))
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParserError> {
+        // expression_statement -> expression ";"
+        let expr = self.expression()?;
+        self.consume(
+            TokenType::Semicolon,
+            ParserErrorKind::MissingSemicolon,
+            "Expected ';' after expression",
+        )?;
+        Ok(Stmt::new_expression(expr))
+    }
+
     fn expression(&mut self) -> Result<Expr, ParserError> {
-        // expression -> equality
-        self.comma()
+        // expression -> comma
+        if self.pratt {
+            self.infix(TokenType::Comma.precedence().unwrap())
+        } else {
+            self.comma()
+        }
+    }
+
+    /// A call argument, which can be anything but a comma expression
+    fn argument(&mut self) -> Result<Expr, ParserError> {
+        if self.pratt {
+            self.infix(TokenType::Interro.precedence().unwrap())
+        } else {
+            self.conditional_expression()
+        }
+    }
+
+    /// Pratt parser for everything from the comma operator down to unary,
+    /// limited to operators binding at least as tightly as `min_precedence`.
+    fn infix(&mut self, min_precedence: u8) -> Result<Expr, ParserError> {
+        let mut expr = self.unary();
+        // Operators are consumed in order of decreasing precedence, so any
+        // level below the last one is still parsing its first operand
+        let mut ceiling = u8::MAX;
+
+        loop {
+            let lhs = match expr {
+                Ok(lhs) => lhs,
+                Err(err) => return Err(self.recover_left_operand(min_precedence, ceiling, err)),
+            };
+            let precedence = match self
+                .peek()
+                .and_then(|token| token.token_type.precedence())
+                .filter(|precedence| *precedence >= min_precedence)
+            {
+                Some(precedence) => precedence,
+                None => return Ok(lhs),
+            };
+            let operator = self.advance().unwrap();
+            expr = self.infix_operator(lhs, operator, precedence);
+            ceiling = precedence;
+        }
+    }
+
+    /// Parse whatever follows an infix operator at the given precedence
+    fn infix_operator(
+        &mut self,
+        lhs: Expr,
+        operator: Token,
+        precedence: u8,
+    ) -> Result<Expr, ParserError> {
+        match operator.token_type {
+            TokenType::Interro => {
+                let mhs = self.nested(&Self::expression)?;
+                let rho = self.ternary_colon(&operator)?;
+                let rhs = self.nested(&|parser: &mut Self| parser.infix(precedence))?;
+                Ok(Expr::new_ternary(lhs, operator, mhs, rho, rhs))
+            }
+            TokenType::InterroInterro => Ok(Expr::new_logical(
+                lhs,
+                operator,
+                self.nested(&|parser: &mut Self| parser.infix(precedence))?,
+            )),
+            _ => {
+                let operand_precedence = match operator.token_type.associativity() {
+                    Some(Associativity::Right) => precedence,
+                    _ => precedence + 1,
+                };
+                Ok(Expr::new_binary(
+                    lhs,
+                    operator,
+                    self.infix(operand_precedence)?,
+                ))
+            }
+        }
+    }
+
+    /// Error recovery matching `binary`: each binary operator level between
+    /// `min_precedence` and `ceiling` that an error bubbles up through, from
+    /// the tightest outwards, gets to claim the operator following it once.
+    fn recover_left_operand(
+        &mut self,
+        min_precedence: u8,
+        mut ceiling: u8,
+        mut err: ParserError,
+    ) -> ParserError {
+        while let Some(precedence) = self
+            .peek()
+            .filter(|token| {
+                !matches!(
+                    token.token_type,
+                    TokenType::Interro | TokenType::InterroInterro
+                )
+            })
+            .and_then(|token| token.token_type.precedence())
+            .filter(|precedence| (min_precedence..ceiling).contains(precedence))
+        {
+            let operator = self.advance().unwrap();
+            let _ = self.infix(precedence + 1);
+            err = missing_left_operand(operator, err);
+            ceiling = precedence;
+        }
+        err
     }
 
     /// Reusable parsing step for rules shaped like
     /// head -> operand ( ( operator1 | operator2 ) operand )*
+    ///
+    /// Only the first operand is parsed here. This frame is on the stack
+    /// several times over for every level of nesting, so whatever follows is
+    /// left to functions that return before recursing any deeper.
     fn binary(
         &mut self,
         operand: &dyn Fn(&mut Self) -> Result<Expr, ParserError>,
         operators: &[TokenType],
     ) -> Result<Expr, ParserError> {
         match operand(self) {
-            Ok(mut expr) => {
-                while let Some(operator) = self.match_one_of(operators) {
-                    expr = Expr::new_binary(expr, operator, operand(self)?);
-                }
-                Ok(expr)
-            }
-            Err(err) => {
-                if let Some(operator) = self.match_one_of(operators) {
-                    // discard right-hand operand ("also parse and discard a
-                    // right-hand operand", quoth the book, but there's not much
-                    // point as long as the parser bails at the first error.)
-                    // TODO: on that note, make it possible to emit multiple parser errors
-                    let _ = operand(self);
-                    let lexeme = operator.lexeme.to_owned();
-                    Err(ParserError {
-                        token: operator,
-                        message: format!(
-                            "Failed to parse left-hand operator for '{}': {}",
-                            &lexeme, err
-                        ),
-                    })
-                } else {
-                    Err(err)
-                }
+            Ok(expr) => self.binary_operators(expr, operand, operators),
+            Err(err) => Err(self.binary_recovery(err, operand, operators)),
+        }
+    }
+
+    /// The ( operator operand )* part of `binary`
+    fn binary_operators(
+        &mut self,
+        mut expr: Expr,
+        operand: &dyn Fn(&mut Self) -> Result<Expr, ParserError>,
+        operators: &[TokenType],
+    ) -> Result<Expr, ParserError> {
+        while let Some(operator) = self.match_one_of(operators) {
+            expr = Expr::new_binary(expr, operator, operand(self)?);
+        }
+        Ok(expr)
+    }
+
+    /// Turn a failure to parse the first operand of `binary` into a missing
+    /// operand error if an operator follows it.
+    fn binary_recovery(
+        &mut self,
+        err: ParserError,
+        operand: &dyn Fn(&mut Self) -> Result<Expr, ParserError>,
+        operators: &[TokenType],
+    ) -> ParserError {
+        match self.match_one_of(operators) {
+            Some(operator) => {
+                // discard right-hand operand ("also parse and discard a
+                // right-hand operand", quoth the book, but there's not much
+                // point as long as the parser bails at the first error.)
+                // TODO: on that note, make it possible to emit multiple parser errors
+                let _ = operand(self);
+                missing_left_operand(operator, err)
             }
+            None => err,
         }
     }
 
@@ -115,22 +506,46 @@ impl<'tokens> Parser<'tokens> {
     }
 
     fn conditional_expression(&mut self) -> Result<Expr, ParserError> {
-        // conditional_expression -> equality ( "?" expression ":" conditional_expression )?
+        // conditional_expression -> coalesce ( "?" expression ":" conditional_expression )?
 
-        let mut expr = self.equality()?;
-        if let Some(left_hand_operator) = self.match_one_of(&[TokenType::Interro]) {
-            expr = Expr::new_ternary(
-                expr,
-                left_hand_operator,
-                self.expression()?,
-                self.consume(TokenType::Colon, "Expected :")?,
-                self.conditional_expression()?,
-            );
+        let mut expr = self.coalesce()?;
+        if let Some(lho) = self.match_one_of(&[TokenType::Interro]) {
+            let mhs = self.nested(&Self::expression)?;
+            let rho = self.ternary_colon(&lho)?;
+            let rhs = self.nested(&Self::conditional_expression)?;
+            expr = Expr::new_ternary(expr, lho, mhs, rho, rhs);
         }
 
         Ok(expr)
     }
 
+    /// The ':' of the ternary started by `lho`, which the error points back to
+    /// as it may be some way off in a long or nested ternary.
+    fn ternary_colon(&mut self, lho: &Token) -> Result<Token, ParserError> {
+        self.consume(
+            TokenType::Colon,
+            ParserErrorKind::MissingColon,
+            &format!(
+                "Expected ':' for the '?' at line {}, column {}",
+                lho.line, lho.column
+            ),
+        )
+    }
+
+    fn coalesce(&mut self) -> Result<Expr, ParserError> {
+        // coalesce -> equality ( "??" coalesce )?
+        let expr = self.equality()?;
+        if let Some(operator) = self.match_one_of(&[TokenType::InterroInterro]) {
+            Ok(Expr::new_logical(
+                expr,
+                operator,
+                self.nested(&Self::coalesce)?,
+            ))
+        } else {
+            Ok(expr)
+        }
+    }
+
     fn equality(&mut self) -> Result<Expr, ParserError> {
         // equality -> comparison ( ( "!=" | "==" ) comparison )*
         self.binary(
@@ -140,9 +555,9 @@ impl<'tokens> Parser<'tokens> {
     }
 
     fn comparison(&mut self) -> Result<Expr, ParserError> {
-        // term ( ( ">" | ">=" | "<" | "<=" ) term )*
+        // bitwise_or ( ( ">" | ">=" | "<" | "<=" ) bitwise_or )*
         self.binary(
-            &Self::term,
+            &Self::bitwise_or,
             &[
                 TokenType::Greater,
                 TokenType::GreaterEqual,
@@ -152,90 +567,713 @@ impl<'tokens> Parser<'tokens> {
         )
     }
 
+    // Unlike C, the bitwise operators bind tighter than comparisons, so that
+    // `a & 1 == 1` does what it looks like.
+    fn bitwise_or(&mut self) -> Result<Expr, ParserError> {
+        // bitwise_xor ( "|" bitwise_xor )*
+        self.binary(&Self::bitwise_xor, &[TokenType::Pipe])
+    }
+
+    fn bitwise_xor(&mut self) -> Result<Expr, ParserError> {
+        // bitwise_and ( "^" bitwise_and )*
+        self.binary(&Self::bitwise_and, &[TokenType::Caret])
+    }
+
+    fn bitwise_and(&mut self) -> Result<Expr, ParserError> {
+        // shift ( "&" shift )*
+        self.binary(&Self::shift, &[TokenType::Ampersand])
+    }
+
+    fn shift(&mut self) -> Result<Expr, ParserError> {
+        // term ( ( "<<" | ">>" ) term )*
+        self.binary(
+            &Self::term,
+            &[TokenType::LessLess, TokenType::GreaterGreater],
+        )
+    }
+
     fn term(&mut self) -> Result<Expr, ParserError> {
         // factor ( ( "-" | "+" ) factor )*
         self.binary(&Self::factor, &[TokenType::Plus, TokenType::Minus])
     }
 
     fn factor(&mut self) -> Result<Expr, ParserError> {
-        // unary ( ( "/" | "*" ) factor )*
-        self.binary(&Self::unary, &[TokenType::Slash, TokenType::Star])
+        // unary ( ( "/" | "*" | "%" ) factor )*
+        self.binary(
+            &Self::unary,
+            &[TokenType::Slash, TokenType::Star, TokenType::Percent],
+        )
     }
 
     fn unary(&mut self) -> Result<Expr, ParserError> {
-        // ( ( "!" | "-" ) unary ) | primary
+        // ( ( "!" | "-" ) unary ) | call
+        // A seeded group is the start of a call, whatever follows it
+        if self.seed.is_some() {
+            return self.call();
+        }
         if let Some(operator) = self.match_one_of(&[TokenType::Bang, TokenType::Minus]) {
-            Ok(Expr::new_unary(operator, self.unary()?))
+            Ok(Expr::new_unary(operator, self.nested(&Self::unary)?))
         } else {
-            self.primary()
+            self.call()
+        }
+    }
+
+    fn call(&mut self) -> Result<Expr, ParserError> {
+        // primary ( "(" arguments? ")" | "." IDENTIFIER )*
+        let mut expr = self.primary()?;
+
+        while let Some(token) = self.match_one_of(&[TokenType::LeftParen, TokenType::Dot]) {
+            if token.token_type == TokenType::LeftParen {
+                expr = self.finish_call(expr)?;
+            } else {
+                let name = self.consume(
+                    TokenType::Identifier,
+                    ParserErrorKind::ExpectedPropertyName,
+                    "Expected property name after '.'",
+                )?;
+                expr = Expr::new_get(expr, name);
+            }
         }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParserError> {
+        // arguments -> conditional_expression ( "," conditional_expression )* ","?
+        // Arguments sit just below the comma operator, which would otherwise
+        // swallow the separators.
+        let mut arguments = Vec::new();
+
+        if !self.check(TokenType::RightParen) {
+            arguments.push(self.nested(&Self::argument)?);
+            while self.match_one_of(&[TokenType::Comma]).is_some() {
+                if self.check(TokenType::RightParen) {
+                    break;
+                }
+                if arguments.len() == MAX_ARGUMENTS {
+                    if let Some(token) = self.peek() {
+                        self.errors.push(ParserError {
+                            kind: ParserErrorKind::TooManyArguments,
+                            token,
+                            message: format!("Can't have more than {} arguments", MAX_ARGUMENTS),
+                        });
+                    }
+                }
+                arguments.push(self.nested(&Self::argument)?);
+            }
+        }
+
+        let paren = self.consume(
+            TokenType::RightParen,
+            ParserErrorKind::MissingRightParen,
+            "Unterminated argument list",
+        )?;
+        Ok(Expr::new_call(callee, paren, arguments))
     }
 
     fn primary(&mut self) -> Result<Expr, ParserError> {
-        // NUMBER | STRING | TRUE | FALSE | NIL | "(" expression ")"
+        // NUMBER | STRING | CHAR | TRUE | FALSE | NIL | IDENTIFIER | "(" expression ")"
 
-        if let Some(primary) = self.match_one_of(&[
+        if let Some(group) = self.seed.take() {
+            Ok(group)
+        } else if let Some(primary) = self.match_one_of(&[
             TokenType::Number,
             TokenType::String,
+            TokenType::Char,
             TokenType::True,
             TokenType::False,
             TokenType::Nil,
         ]) {
-            Ok(Expr::new_literal(primary.literal.unwrap()))
+            let value = primary.literal.unwrap();
+            // Keep the spelling of numbers that print differently, like 4.0,
+            // so that tools can reproduce the source
+            let lexeme = match value {
+                Literal::Number(_) if value.to_string() != primary.lexeme => Some(primary.lexeme),
+                _ => None,
+            };
+            Ok(Expr::new_literal(value, lexeme))
+        } else if let Some(name) = self.match_one_of(&[TokenType::Identifier]) {
+            Ok(Expr::new_variable(name))
+        } else if self.block_expressions && self.match_one_of(&[TokenType::LeftBrace]).is_some() {
+            self.nested(&Self::block_expression)
         } else {
             self.consume(
                 TokenType::LeftParen,
-                "Expected one of Number, String, True, False, Nil, or (Expr)",
+                ParserErrorKind::ExpectedExpression,
+                "Expected one of Number, String, Char, True, False, Nil, Identifier, or (Expr)",
             )?;
+            let depth = self.depth;
+            let result = self.group();
+            self.depth = depth;
+            result
+        }
+    }
+
+    /// The rest of a group after its '('. Rather than recursing for each
+    /// group directly inside another, like `((1) + 2)`, the open groups are
+    /// counted and closed again one at a time. Each closed group is seeded as
+    /// the first primary of the one around it, whose expression then carries
+    /// on from there. Every group is a level deeper than the one around it,
+    /// which the caller restores afterwards.
+    fn group(&mut self) -> Result<Expr, ParserError> {
+        self.descend()?;
+        let mut open = 1;
+        while self.match_one_of(&[TokenType::LeftParen]).is_some() {
+            self.descend()?;
+            open += 1;
+        }
+
+        let mut expr = self.expression()?;
+        loop {
+            self.consume(
+                TokenType::RightParen,
+                ParserErrorKind::MissingRightParen,
+                "Unterminated (Expr)",
+            )?;
+            expr = Expr::new_grouping(expr);
+            open -= 1;
+            self.depth -= 1;
+            if open == 0 {
+                return Ok(expr);
+            }
+            self.seed = Some(expr);
+            expr = self.expression()?;
+        }
+    }
+
+    fn block_expression(&mut self) -> Result<Expr, ParserError> {
+        // block_expression -> "{" statement* expression "}"
+        let mut statements = Vec::new();
+        loop {
+            if self.match_one_of(&[TokenType::Print]).is_some() {
+                statements.push(self.print_statement()?);
+                continue;
+            }
+
             let expr = self.expression()?;
-            self.consume(TokenType::RightParen, "Unterminated (Expr)")?;
-            Ok(Expr::new_grouping(expr))
+            if self.match_one_of(&[TokenType::Semicolon]).is_some() {
+                statements.push(Stmt::new_expression(expr));
+            } else {
+                self.consume(
+                    TokenType::RightBrace,
+                    ParserErrorKind::MissingRightBrace,
+                    "Expected ';' or '}' after expression",
+                )?;
+                return Ok(Expr::new_block(statements, expr));
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::token::Literal;
-
     use super::*;
     #[test]
     fn parse_plus() {
         let tokens = [
-            Token::new_literal(TokenType::True, "true", Literal::Bool(true), 0),
-            Token::new(TokenType::Plus, "+", 1),
-            Token::new_literal(TokenType::Number, "6.2", Literal::Number(6.2), 2),
-            Token::new(TokenType::Eof, "", 3),
+            Token::new_literal(TokenType::True, "true", Literal::Bool(true), 0, 1),
+            Token::new(TokenType::Plus, "+", 1, 1),
+            Token::new_literal(TokenType::Number, "6.2", Literal::Number(6.2), 2, 1),
+            Token::new(TokenType::Eof, "", 3, 1),
         ];
-        let mut under_test = Parser { tokens: &tokens };
+        let mut under_test = Parser::new(&tokens);
 
         assert_eq!(
             under_test.parse().unwrap(),
             Expr::new_binary(
-                Expr::new_literal(Literal::Bool(true)),
+                Expr::new_literal(Literal::Bool(true), None),
                 Token {
                     token_type: TokenType::Plus,
                     lexeme: "+".into(),
                     line: 1,
+                    column: 1,
                     literal: None
                 },
-                Expr::new_literal(Literal::Number(6.2))
+                Expr::new_literal(Literal::Number(6.2), None)
             )
         );
     }
 
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Parser::new needs tokens that end in Eof")]
+    fn tokens_without_eof() {
+        let tokens = [Token::new_literal(
+            TokenType::Number,
+            "1",
+            Literal::Number(1.0),
+            1,
+            1,
+        )];
+        Parser::new(&tokens);
+    }
+
     #[test]
     fn binary_missing_operand() {
         let tokens = [
-            Token::new(TokenType::Plus, "+", 1),
-            Token::new_literal(TokenType::Number, "6.2", Literal::Number(6.2), 2),
-            Token::new(TokenType::Eof, "", 3),
+            Token::new(TokenType::Plus, "+", 1, 1),
+            Token::new_literal(TokenType::Number, "6.2", Literal::Number(6.2), 2, 1),
+            Token::new(TokenType::Eof, "", 3, 1),
         ];
-        let mut under_test = Parser { tokens: &tokens };
+        let mut under_test = Parser::new(&tokens);
         // Has anyone made a site for error message gore yet?
         assert_eq!(
             under_test.parse().unwrap_err().message,
-            "Failed to parse left-hand operator for '+': 1: Plus: Unexpected token '+'. Expected one of Number, String, True, False, Nil, or (Expr)"
+            "Failed to parse left-hand operator for '+': E1002: 1: Plus: Unexpected token '+'. Expected one of Number, String, Char, True, False, Nil, Identifier, or (Expr)"
         );
     }
+
+    #[test]
+    fn chained_call_and_get() {
+        let tokens = crate::scanner::Scanner::new("a.b.c()")
+            .scan_tokens()
+            .unwrap();
+        let mut under_test = Parser::new(&tokens);
+
+        assert_eq!(
+            under_test.parse().unwrap(),
+            Expr::new_call(
+                Expr::new_get(
+                    Expr::new_get(
+                        Expr::new_variable(Token::new(TokenType::Identifier, "a", 1, 1)),
+                        Token::new(TokenType::Identifier, "b", 1, 3)
+                    ),
+                    Token::new(TokenType::Identifier, "c", 1, 5)
+                ),
+                Token::new(TokenType::RightParen, ")", 1, 7),
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn get_without_name() {
+        let tokens = crate::scanner::Scanner::new("a.(b)").scan_tokens().unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::ExpectedPropertyName);
+        assert_eq!(
+            error.message,
+            "Unexpected token '('. Expected property name after '.'"
+        );
+    }
+
+    #[test]
+    fn too_many_arguments() {
+        let arguments = (0..256).map(|n| n.to_string()).collect::<Vec<_>>();
+        let source = format!("f({}) + 1", arguments.join(", "));
+        let tokens = crate::scanner::Scanner::new(&source).scan_tokens().unwrap();
+        let mut under_test = Parser::new(&tokens);
+
+        let error = under_test.parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::TooManyArguments);
+        assert_eq!(error.token.lexeme, "255");
+        assert_eq!(error.message, "Can't have more than 255 arguments");
+        // The parser carried on to the end rather than stopping at the error
+        assert!(under_test.is_at_end());
+    }
+
+    #[test]
+    fn too_deeply_nested() {
+        let source = format!("{}1{}", "(".repeat(10000), ")".repeat(10000));
+        let tokens = crate::scanner::Scanner::new(&source).scan_tokens().unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::TooDeeplyNested);
+        // The contents of the group after the last one that fits are too deep
+        assert_eq!(error.token.column, DEFAULT_MAX_DEPTH + 2);
+
+        let tokens = crate::scanner::Scanner::new(&format!("{}true", "!".repeat(10000)))
+            .scan_tokens()
+            .unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::TooDeeplyNested);
+    }
+
+    #[test]
+    fn custom_max_depth() {
+        let tokens = crate::scanner::Scanner::new("f(-(1))")
+            .scan_tokens()
+            .unwrap();
+        assert!(Parser::new(&tokens).max_depth(3).parse().is_ok());
+        let error = Parser::new(&tokens).max_depth(2).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::TooDeeplyNested);
+        assert_eq!(error.message, "Expression nested more than 2 levels deep");
+    }
+
+    #[test]
+    fn parse_from_iterator() {
+        let source = "f(1, -2).x ? \"a\" : (b, c) + 3 % 4";
+        let tokens = crate::scanner::Scanner::new(source).scan_tokens().unwrap();
+
+        let from_slice = Parser::new(&tokens).parse().unwrap();
+        let from_iterator = Parser::from_tokens(tokens.into_iter()).parse().unwrap();
+        assert_eq!(from_slice, from_iterator);
+    }
+
+    #[test]
+    fn parse_one_expression() {
+        let tokens = crate::scanner::Scanner::new("1 + 2; 3")
+            .scan_tokens()
+            .unwrap();
+        let mut under_test = Parser::new(&tokens);
+
+        assert_eq!(
+            under_test.parse_expression().unwrap(),
+            Expr::new_binary(
+                Expr::new_literal(Literal::Number(1.0), None),
+                Token::new(TokenType::Plus, "+", 1, 3),
+                Expr::new_literal(Literal::Number(2.0), None)
+            )
+        );
+        assert_eq!(
+            under_test.current(),
+            Some(&Token::new(TokenType::Semicolon, ";", 1, 6))
+        );
+        assert!(!under_test.is_at_end());
+
+        under_test.advance();
+        assert_eq!(
+            under_test.parse_expression().unwrap(),
+            Expr::new_literal(Literal::Number(3.0), None)
+        );
+        assert!(under_test.is_at_end());
+    }
+
+    #[test]
+    fn trailing_tokens() {
+        let tokens = crate::scanner::Scanner::new("1 + 2 extra")
+            .scan_tokens()
+            .unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::ExpectedEndOfFile);
+        assert_eq!(
+            error.token,
+            Token::new(TokenType::Identifier, "extra", 1, 7)
+        );
+        assert_eq!(
+            error.message,
+            "Unexpected token 'extra'. Expected end of file after expression"
+        );
+
+        let tokens = crate::scanner::Scanner::new("(1))").scan_tokens().unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::ExpectedEndOfFile);
+        assert_eq!(error.token.column, 4);
+    }
+
+    #[test]
+    fn error_span() {
+        let tokens = crate::scanner::Scanner::new("1 == == 2")
+            .scan_tokens()
+            .unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.token.lexeme, "==");
+        assert_eq!(error.span(), 6..8);
+
+        let tokens = crate::scanner::Scanner::new("(a + b longer_name)")
+            .scan_tokens()
+            .unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.span(), 8..19);
+    }
+
+    #[test]
+    fn parse_statements() {
+        let tokens = crate::scanner::Scanner::new("print 1;\nf();")
+            .scan_tokens()
+            .unwrap();
+
+        assert_eq!(
+            Parser::new(&tokens).parse_program().unwrap(),
+            vec![
+                Stmt::new_print(vec![Expr::new_literal(Literal::Number(1.0), None)]),
+                Stmt::new_expression(Expr::new_call(
+                    Expr::new_variable(Token::new(TokenType::Identifier, "f", 2, 1)),
+                    Token::new(TokenType::RightParen, ")", 2, 3),
+                    vec![]
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn statement_without_semicolon() {
+        let tokens = crate::scanner::Scanner::new("print 1\nprint 2;")
+            .scan_tokens()
+            .unwrap();
+        let error = Parser::new(&tokens).parse_program().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::MissingSemicolon);
+        assert_eq!(
+            error.message,
+            "Unexpected token 'print'. Expected ';' after value"
+        );
+    }
+
+    #[test]
+    fn block_expression() {
+        let tokens = crate::scanner::Scanner::new("{ print 1; f(); 2 } * 3")
+            .scan_tokens()
+            .unwrap();
+
+        assert_eq!(
+            Parser::new(&tokens)
+                .block_expressions(true)
+                .parse()
+                .unwrap(),
+            Expr::new_binary(
+                Expr::new_block(
+                    vec![
+                        Stmt::new_print(vec![Expr::new_literal(Literal::Number(1.0), None)]),
+                        Stmt::new_expression(Expr::new_call(
+                            Expr::new_variable(Token::new(TokenType::Identifier, "f", 1, 12)),
+                            Token::new(TokenType::RightParen, ")", 1, 14),
+                            vec![]
+                        )),
+                    ],
+                    Expr::new_literal(Literal::Number(2.0), None)
+                ),
+                Token::new(TokenType::Star, "*", 1, 21),
+                Expr::new_literal(Literal::Number(3.0), None)
+            )
+        );
+
+        // Braces aren't expressions unless asked for
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::ExpectedExpression);
+    }
+
+    #[test]
+    fn block_expression_without_value() {
+        let tokens = crate::scanner::Scanner::new("{ 1; 2; }")
+            .scan_tokens()
+            .unwrap();
+        let error = Parser::new(&tokens)
+            .block_expressions(true)
+            .parse()
+            .unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::ExpectedExpression);
+
+        let tokens = crate::scanner::Scanner::new("{ 1 2 }")
+            .scan_tokens()
+            .unwrap();
+        let error = Parser::new(&tokens)
+            .block_expressions(true)
+            .parse()
+            .unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::MissingRightBrace);
+        assert_eq!(
+            error.message,
+            "Unexpected token '2'. Expected ';' or '}' after expression"
+        );
+    }
+
+    #[test]
+    fn coalesce() {
+        let tokens = crate::scanner::Scanner::new("a ?? b ?? 1 == 2 ? 3 : 4")
+            .scan_tokens()
+            .unwrap();
+
+        // ?? is right-associative and binds tighter than ?: but looser than ==
+        assert_eq!(
+            Parser::new(&tokens).parse().unwrap(),
+            Expr::new_ternary(
+                Expr::new_logical(
+                    Expr::new_variable(Token::new(TokenType::Identifier, "a", 1, 1)),
+                    Token::new(TokenType::InterroInterro, "??", 1, 3),
+                    Expr::new_logical(
+                        Expr::new_variable(Token::new(TokenType::Identifier, "b", 1, 6)),
+                        Token::new(TokenType::InterroInterro, "??", 1, 8),
+                        Expr::new_binary(
+                            Expr::new_literal(Literal::Number(1.0), None),
+                            Token::new(TokenType::EqualEqual, "==", 1, 13),
+                            Expr::new_literal(Literal::Number(2.0), None)
+                        )
+                    )
+                ),
+                Token::new(TokenType::Interro, "?", 1, 18),
+                Expr::new_literal(Literal::Number(3.0), None),
+                Token::new(TokenType::Colon, ":", 1, 22),
+                Expr::new_literal(Literal::Number(4.0), None)
+            )
+        );
+    }
+
+    #[test]
+    fn pratt_matches_recursive_descent() {
+        let sources = [
+            "1 + 2 * 3 - 4 / 5 % 6",
+            "1 - 2 - 3, 4, 5",
+            "a == b != c < d >= -e",
+            "!a ?? b ?? c == d ? e, f : g ? h : i",
+            "a ? b : c ?? d, e",
+            "f(1, 2 ? 3 : 4, (5, 6)).x.y(z) * 2",
+            "(((1 + 2)))",
+            "1 | 2 ^ 3 & 4 << 5 >> 6 + 7 < 8 | 9",
+            "+ 1",
+            "1 + * 2",
+            "1 * + 2 == 3",
+            "(+ 1) * 2 + 3",
+            "a ? b",
+            "a ? b ? c : d",
+            "a ?? , b",
+            "1 + 2 == , 3",
+            "- + 1 == 2, 3",
+            "f(1, 2",
+            "f(1, 2,)",
+        ];
+
+        for source in sources {
+            let tokens = crate::scanner::Scanner::new(source).scan_tokens().unwrap();
+            let recursive_descent = Parser::new(&tokens).parse();
+            let pratt = Parser::new(&tokens).pratt(true).parse();
+            assert_eq!(
+                format!("{:?}", recursive_descent),
+                format!("{:?}", pratt),
+                "{}",
+                source
+            );
+        }
+
+        let nested = format!("{}1{}", "(".repeat(200), ")".repeat(200));
+        let tokens = crate::scanner::Scanner::new(&nested).scan_tokens().unwrap();
+        let error = Parser::new(&tokens).pratt(true).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::TooDeeplyNested);
+    }
+
+    /// Take apart a tree of nested groups one level at a time, as dropping it
+    /// in one go would recurse as deep as it goes
+    fn grouping_depth(mut expr: Expr) -> (usize, Expr) {
+        let mut depth = 0;
+        while let Expr::Grouping { expression } = expr {
+            expr = *expression;
+            depth += 1;
+        }
+        (depth, expr)
+    }
+
+    #[test]
+    fn deeply_nested_groups() {
+        // Only the limit stops these from parsing, not the stack
+        let source = format!("{}1{}", "(".repeat(50000), ")".repeat(50000));
+        let tokens = crate::scanner::Scanner::new(&source).scan_tokens().unwrap();
+        for pratt in [false, true] {
+            let expr = Parser::new(&tokens)
+                .pratt(pratt)
+                .max_depth(50000)
+                .parse()
+                .unwrap();
+            let (depth, innermost) = grouping_depth(expr);
+            assert_eq!(depth, 50000);
+            assert_eq!(innermost, Expr::new_literal(Literal::Number(1.0), None));
+        }
+
+        let error = Parser::new(&tokens).max_depth(49999).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::TooDeeplyNested);
+    }
+
+    #[test]
+    fn deeply_nested_groups_in_operands() {
+        let source = format!("{}1{}", "1+(".repeat(10000), ")".repeat(10000));
+        let tokens = crate::scanner::Scanner::new(&source).scan_tokens().unwrap();
+        for pratt in [false, true] {
+            let error = Parser::new(&tokens).pratt(pratt).parse().unwrap_err();
+            assert_eq!(error.kind, ParserErrorKind::TooDeeplyNested);
+        }
+    }
+
+    #[test]
+    fn groups_continue_after_inner_group() {
+        let parse = |source: &str| {
+            let tokens = crate::scanner::Scanner::new(source).scan_tokens().unwrap();
+            let expr = Parser::new(&tokens).parse().unwrap();
+            crate::ast_printer::AstPrinter::default().print(&expr)
+        };
+        assert_eq!(parse("((1) - 2)"), "(group (- (group 1) 2))");
+        assert_eq!(
+            parse("(((f)(1).x) * 2, 3)"),
+            "(group (, (* (group (. (call (group f) 1) x)) 2) 3))"
+        );
+        assert_eq!(parse("(((1)))"), "(group (group (group 1)))");
+
+        let tokens = crate::scanner::Scanner::new("((1) - 2").scan_tokens().unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::MissingRightParen);
+        assert_eq!(error.message, "Unexpected end of file. Unterminated (Expr)");
+    }
+
+    #[test]
+    fn bitwise_precedence() {
+        let tokens = crate::scanner::Scanner::new("1 | 2 ^ 3 & 4 << 5 + 6 == 7")
+            .scan_tokens()
+            .unwrap();
+        let number = |n| Expr::new_literal(Literal::Number(n), None);
+
+        assert_eq!(
+            Parser::new(&tokens).parse().unwrap(),
+            Expr::new_binary(
+                Expr::new_binary(
+                    number(1.0),
+                    Token::new(TokenType::Pipe, "|", 1, 3),
+                    Expr::new_binary(
+                        number(2.0),
+                        Token::new(TokenType::Caret, "^", 1, 7),
+                        Expr::new_binary(
+                            number(3.0),
+                            Token::new(TokenType::Ampersand, "&", 1, 11),
+                            Expr::new_binary(
+                                number(4.0),
+                                Token::new(TokenType::LessLess, "<<", 1, 15),
+                                Expr::new_binary(
+                                    number(5.0),
+                                    Token::new(TokenType::Plus, "+", 1, 20),
+                                    number(6.0)
+                                )
+                            )
+                        )
+                    )
+                ),
+                Token::new(TokenType::EqualEqual, "==", 1, 24),
+                number(7.0)
+            )
+        );
+    }
+
+    #[test]
+    fn trailing_comma_in_arguments() {
+        let parse = |source| {
+            let tokens = crate::scanner::Scanner::new(source).scan_tokens().unwrap();
+            Parser::new(&tokens).parse()
+        };
+
+        assert_eq!(
+            parse("f(1, 2,)").unwrap(),
+            Expr::new_call(
+                Expr::new_variable(Token::new(TokenType::Identifier, "f", 1, 1)),
+                Token::new(TokenType::RightParen, ")", 1, 8),
+                vec![
+                    Expr::new_literal(Literal::Number(1.0), None),
+                    Expr::new_literal(Literal::Number(2.0), None)
+                ]
+            )
+        );
+        // Only one trailing comma, and only after an argument
+        assert!(parse("f(,)").is_err());
+        assert!(parse("f(1,,)").is_err());
+        // Outside of argument lists a trailing comma is still a missing operand
+        assert_eq!(
+            parse("1, 2,").unwrap_err().kind,
+            ParserErrorKind::ExpectedExpression
+        );
+        assert_eq!(
+            parse("(1, 2,)").unwrap_err().kind,
+            ParserErrorKind::ExpectedExpression
+        );
+    }
+
+    #[test]
+    fn max_arguments() {
+        let arguments = (0..255).map(|n| n.to_string()).collect::<Vec<_>>();
+        let source = format!("f({})", arguments.join(", "));
+        let tokens = crate::scanner::Scanner::new(&source).scan_tokens().unwrap();
+        assert!(Parser::new(&tokens).parse().is_ok());
+    }
 }
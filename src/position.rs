@@ -0,0 +1,209 @@
+use crate::{expr::Expr, stmt::Stmt, token::Token};
+
+/// Hand-written additions to the generated Expr, for finding out where in the
+/// source an expression came from, or for ignoring exactly that.
+impl Expr {
+    /// The token that best identifies this expression in diagnostics: the
+    /// operator for operations, the name for variables and properties and the
+    /// closing parenthesis for calls. Literals don't keep their token, so they
+    /// have none, and groupings defer to what they contain.
+    pub fn primary_token(&self) -> Option<&Token> {
+        match self {
+            Expr::Binary { operator, .. } => Some(operator),
+            Expr::Ternary { lho, .. } => Some(lho),
+            Expr::Logical { operator, .. } => Some(operator),
+            Expr::Call { paren, .. } => Some(paren),
+            Expr::Get { name, .. } => Some(name),
+            Expr::Grouping { expression } => expression.primary_token(),
+            Expr::Literal { .. } => None,
+            Expr::Unary { operator, .. } => Some(operator),
+            Expr::Variable { name } => Some(name),
+            Expr::Block { value, .. } => value.primary_token(),
+        }
+    }
+
+    /// Equality that ignores where in the source the tokens came from, so that
+    /// the same expression parsed from differently laid out sources compares
+    /// equal.
+    pub fn structurally_eq(&self, other: &Expr) -> bool {
+        let all_eq = |lhs: &[Expr], rhs: &[Expr]| {
+            lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(l, r)| l.structurally_eq(r))
+        };
+
+        match (self, other) {
+            (
+                Expr::Binary { lhs, operator, rhs },
+                Expr::Binary {
+                    lhs: other_lhs,
+                    operator: other_operator,
+                    rhs: other_rhs,
+                },
+            )
+            | (
+                Expr::Logical { lhs, operator, rhs },
+                Expr::Logical {
+                    lhs: other_lhs,
+                    operator: other_operator,
+                    rhs: other_rhs,
+                },
+            ) => {
+                tokens_eq(operator, other_operator)
+                    && lhs.structurally_eq(other_lhs)
+                    && rhs.structurally_eq(other_rhs)
+            }
+            (
+                Expr::Ternary {
+                    lhs,
+                    lho,
+                    mhs,
+                    rho,
+                    rhs,
+                },
+                Expr::Ternary {
+                    lhs: other_lhs,
+                    lho: other_lho,
+                    mhs: other_mhs,
+                    rho: other_rho,
+                    rhs: other_rhs,
+                },
+            ) => {
+                tokens_eq(lho, other_lho)
+                    && tokens_eq(rho, other_rho)
+                    && lhs.structurally_eq(other_lhs)
+                    && mhs.structurally_eq(other_mhs)
+                    && rhs.structurally_eq(other_rhs)
+            }
+            (
+                Expr::Call {
+                    callee,
+                    paren,
+                    arguments,
+                },
+                Expr::Call {
+                    callee: other_callee,
+                    paren: other_paren,
+                    arguments: other_arguments,
+                },
+            ) => {
+                tokens_eq(paren, other_paren)
+                    && callee.structurally_eq(other_callee)
+                    && all_eq(arguments, other_arguments)
+            }
+            (
+                Expr::Get { object, name },
+                Expr::Get {
+                    object: other_object,
+                    name: other_name,
+                },
+            ) => tokens_eq(name, other_name) && object.structurally_eq(other_object),
+            (
+                Expr::Grouping { expression },
+                Expr::Grouping {
+                    expression: other_expression,
+                },
+            ) => expression.structurally_eq(other_expression),
+            (Expr::Literal { .. }, Expr::Literal { .. }) => self == other,
+            (
+                Expr::Unary { operator, operand },
+                Expr::Unary {
+                    operator: other_operator,
+                    operand: other_operand,
+                },
+            ) => tokens_eq(operator, other_operator) && operand.structurally_eq(other_operand),
+            (Expr::Variable { name }, Expr::Variable { name: other_name }) => {
+                tokens_eq(name, other_name)
+            }
+            (
+                Expr::Block { statements, value },
+                Expr::Block {
+                    statements: other_statements,
+                    value: other_value,
+                },
+            ) => {
+                statements.len() == other_statements.len()
+                    && statements
+                        .iter()
+                        .zip(other_statements)
+                        .all(|(l, r)| statements_eq(l, r))
+                    && value.structurally_eq(other_value)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Compare statements apart from where their tokens came from
+fn statements_eq(lhs: &Stmt, rhs: &Stmt) -> bool {
+    match (lhs, rhs) {
+        (
+            Stmt::Expression { expression },
+            Stmt::Expression {
+                expression: other_expression,
+            },
+        ) => expression.structurally_eq(other_expression),
+        (
+            Stmt::Print { expressions },
+            Stmt::Print {
+                expressions: other_expressions,
+            },
+        ) => {
+            expressions.len() == other_expressions.len()
+                && expressions
+                    .iter()
+                    .zip(other_expressions)
+                    .all(|(l, r)| l.structurally_eq(r))
+        }
+        _ => false,
+    }
+}
+
+/// Compare tokens apart from their line and column
+fn tokens_eq(lhs: &Token, rhs: &Token) -> bool {
+    lhs.token_type == rhs.token_type && lhs.lexeme == rhs.lexeme && lhs.literal == rhs.literal
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner, token::TokenType};
+
+    fn parse(source: &str) -> Expr {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        Parser::new(&tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn primary_tokens() {
+        assert_eq!(
+            parse("1 + 2").primary_token(),
+            Some(&Token::new(TokenType::Plus, "+", 1, 3))
+        );
+        assert_eq!(
+            parse("-x").primary_token(),
+            Some(&Token::new(TokenType::Minus, "-", 1, 1))
+        );
+        assert_eq!(
+            parse("\n  x").primary_token(),
+            Some(&Token::new(TokenType::Identifier, "x", 2, 3))
+        );
+        assert_eq!(
+            parse("(a.b)").primary_token(),
+            Some(&Token::new(TokenType::Identifier, "b", 1, 4))
+        );
+        assert_eq!(parse("(1)").primary_token(), None);
+    }
+
+    #[test]
+    fn structural_equality() {
+        let source = "f(a, -b).c ? 1 + 2 : x ?? (y)";
+        let indented = "\n    f( a,\n\t-b ).c\n  ? 1 +  2\n  : x ?? ( y )";
+        assert_ne!(parse(source), parse(indented));
+        assert!(parse(source).structurally_eq(&parse(indented)));
+
+        assert!(!parse("1 + 2").structurally_eq(&parse("1 - 2")));
+        assert!(!parse("a").structurally_eq(&parse("b")));
+        assert!(!parse("f(1)").structurally_eq(&parse("f(1, 2)")));
+        assert!(!parse("(1)").structurally_eq(&parse("1")));
+        assert!(!parse("4").structurally_eq(&parse("4.0")));
+    }
+}
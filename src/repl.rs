@@ -0,0 +1,225 @@
+//! Running the entries of an interactive session one after the other.
+
+use std::{fmt::Debug, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+    ast_printer::AstPrinter,
+    execute_with,
+    expr::Expr,
+    interpreter::{Interpreter, RuntimeError, Value},
+    parse_expression, parse_program, run_phases,
+    scanner::{source_line, Scanner},
+    token::{Token, TokenType},
+    Error, Timings,
+};
+
+/// How `Repl::show` presents the value of an expression entry
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Mode {
+    /// Just the value, like `3`
+    #[default]
+    Value,
+    /// The value and its type, like `3 : number`
+    Typed,
+    /// The parsed expression rather than its value, like `(+ 1 2)`
+    Ast,
+}
+
+impl Mode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Value => "value",
+            Mode::Typed => "typed",
+            Mode::Ast => "ast",
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = MetaCommandError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "value" => Ok(Mode::Value),
+            "typed" => Ok(Mode::Typed),
+            "ast" => Ok(Mode::Ast),
+            other => Err(MetaCommandError::UnknownMode(other.into())),
+        }
+    }
+}
+
+/// Entries starting with ':' that don't make sense
+#[derive(Debug, Error, PartialEq)]
+pub enum MetaCommandError {
+    #[error("Unknown command '{0}', expected :mode")]
+    UnknownCommand(String),
+    #[error("Unknown mode '{0}', expected value, typed or ast")]
+    UnknownMode(String),
+}
+
+/// State carried over from one REPL entry to the next
+pub struct Repl {
+    /// Line the next entry starts on, so that errors point at the line of
+    /// the session rather than the line of the entry
+    line: usize,
+    mode: Mode,
+    /// Runs every entry, so that what one entry does to the globals is seen
+    /// by the next
+    interpreter: Interpreter,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Repl {
+            line: 1,
+            mode: Mode::default(),
+            interpreter: Interpreter::new(),
+        }
+    }
+}
+
+impl Debug for Repl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Repl")
+            .field("line", &self.line)
+            .field("mode", &self.mode)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl::default()
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// The interpreter that runs every entry, for defining globals before or
+    /// between them
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    /// Run an entry as a program or, if it doesn't parse as one, as a lone
+    /// expression whose value is returned. A print statement is also accepted
+    /// without its semicolon.
+    pub fn run(&mut self, source: &str) -> Result<Option<Value>, Error> {
+        self.run_timed(source).0
+    }
+
+    /// Run an entry like `run`, and also report how long each phase took.
+    pub fn run_timed(&mut self, source: &str) -> (Result<Option<Value>, Error>, Timings) {
+        let (result, timings) = self.run_entry(source);
+        (result.map(|entry| entry.map(|(_, value)| value)), timings)
+    }
+
+    /// Run an entry like `run`, formatting the value of an expression entry
+    /// according to the mode.
+    pub fn show(&mut self, source: &str) -> Result<Option<String>, Error> {
+        self.show_timed(source).0
+    }
+
+    /// Run an entry like `show`, and also report how long each phase took.
+    pub fn show_timed(&mut self, source: &str) -> (Result<Option<String>, Error>, Timings) {
+        let (result, timings) = self.run_entry(source);
+        let result = result.map(|entry| entry.map(|(expr, value)| self.format(&expr, &value)));
+        (result, timings)
+    }
+
+    /// An expression entry and its value, presented according to the mode
+    pub fn format(&self, expr: &Expr, value: &Value) -> String {
+        match self.mode {
+            Mode::Value => value.to_string(),
+            Mode::Typed => format!("{} : {}", value, value.type_name()),
+            Mode::Ast => AstPrinter::default().print(expr),
+        }
+    }
+
+    /// Handle an entry starting with ':', which is never Lox. `:mode` shows
+    /// the current mode and `:mode <name>` switches to another one. Returns
+    /// None for any other entry, to be run instead.
+    pub fn meta_command(&mut self, source: &str) -> Option<Result<String, MetaCommandError>> {
+        let command = source.trim().strip_prefix(':')?;
+        self.line += source.lines().count();
+
+        let mut words = command.split_whitespace();
+        let result = match (words.next(), words.next(), words.next()) {
+            (Some("mode"), None, None) => Ok(format!("mode: {}", self.mode.as_str())),
+            (Some("mode"), Some(name), None) => name.parse().map(|mode| {
+                self.mode = mode;
+                format!("mode: {}", mode.as_str())
+            }),
+            _ => Err(MetaCommandError::UnknownCommand(command.into())),
+        };
+        Some(result)
+    }
+
+    fn run_entry(&mut self, source: &str) -> (Result<Option<(Expr, Value)>, Error>, Timings) {
+        let first_line = self.line;
+        self.line += source.lines().count();
+
+        let interpreter = &mut self.interpreter;
+        let (result, timings) = run_phases(source, first_line, parse_program, |statements| {
+            execute_with(interpreter, statements)
+        });
+        if matches!(result, Err(Error::Parser(_))) {
+            let (result, timings) = run_phases(source, first_line, parse_expression, |expr| {
+                evaluate(interpreter, expr)
+            });
+            if !matches!(result, Err(Error::Parser(_))) {
+                return (result.map(Some), timings);
+            }
+            if let Some(source) = with_semicolon(source) {
+                let (result, timings) =
+                    run_phases(&source, first_line, parse_program, |statements| {
+                        execute_with(interpreter, statements)
+                    });
+                if !matches!(result, Err(Error::Parser(_))) {
+                    return (result.map(|_| None), timings);
+                }
+            }
+        }
+
+        (result.map(|_| None), timings)
+    }
+}
+
+/// A print statement with the semicolon it's missing added after its last
+/// token, if the entry is one. Going by the token rather than the end of the
+/// text keeps the semicolon out of a trailing comment.
+fn with_semicolon(source: &str) -> Option<String> {
+    let tokens = Scanner::new(source).scan_tokens().ok()?;
+    let first = tokens.first()?;
+    // The last token is always Eof
+    let last = tokens.iter().rev().nth(1)?;
+    if first.token_type != TokenType::Print || last.token_type == TokenType::Semicolon {
+        return None;
+    }
+
+    let end = token_offset(source, last)? + last.lexeme.len();
+    Some(format!("{};{}", &source[..end], &source[end..]))
+}
+
+/// Byte offset in `source` of the start of a token scanned from it with the
+/// default options
+fn token_offset(source: &str, token: &Token) -> Option<usize> {
+    let line = source_line(source, token.line)?;
+    // The line is a slice of the source, so their starts give its offset
+    let line_start = line.as_ptr() as usize - source.as_ptr() as usize;
+    let (column, _) = line.char_indices().nth(token.column.checked_sub(1)?)?;
+    Some(line_start + column)
+}
+
+/// Evaluate an expression entry, keeping the expression for `Mode::Ast`
+fn evaluate(interpreter: &mut Interpreter, expr: Expr) -> Result<(Expr, Value), RuntimeError> {
+    let value = interpreter.evaluate(&expr)?;
+    Ok((expr, value))
+}
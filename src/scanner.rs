@@ -1,9 +1,15 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    io::BufRead,
+};
 
 use lazy_static::lazy_static;
 use thiserror::Error;
 
-use crate::token::{Literal, Token, TokenType};
+use crate::{
+    diagnostic::Diagnostic,
+    token::{Literal, Token, TokenType},
+};
 
 lazy_static! {
     static ref KEYWORDS: HashMap<&'static str, TokenType> = HashMap::from([
@@ -27,13 +33,82 @@ lazy_static! {
     ]);
 }
 
+/// Stable diagnostic codes for scanner errors. Scanner codes live in the
+/// E0xxx range, parser codes in E1xxx.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScannerErrorKind {
+    /// E0001
+    UnexpectedCharacter,
+    /// E0002
+    ExpectedToken,
+    /// E0003
+    SourceTooLarge,
+    /// E0004
+    TooManyErrors,
+    /// E0005
+    MalformedLineDirective,
+    /// E0006
+    ReadFailed,
+    /// E0101
+    UnterminatedString,
+    /// E0102
+    UnterminatedBlockComment,
+    /// E0103
+    InvalidNumber,
+    /// E0104
+    InvalidEscape,
+    /// E0105
+    UnterminatedChar,
+    /// E0106
+    InvalidChar,
+}
+
+impl ScannerErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ScannerErrorKind::UnexpectedCharacter => "E0001",
+            ScannerErrorKind::ExpectedToken => "E0002",
+            ScannerErrorKind::SourceTooLarge => "E0003",
+            ScannerErrorKind::TooManyErrors => "E0004",
+            ScannerErrorKind::MalformedLineDirective => "E0005",
+            ScannerErrorKind::ReadFailed => "E0006",
+            ScannerErrorKind::UnterminatedString => "E0101",
+            ScannerErrorKind::UnterminatedBlockComment => "E0102",
+            ScannerErrorKind::InvalidNumber => "E0103",
+            ScannerErrorKind::InvalidEscape => "E0104",
+            ScannerErrorKind::UnterminatedChar => "E0105",
+            ScannerErrorKind::InvalidChar => "E0106",
+        }
+    }
+}
+
 #[derive(Clone, Error, Debug)]
-#[error("{line}: {message}")]
+#[error("{}: {line}:{column}: {message}", kind.code())]
 pub struct ScannerError {
+    kind: ScannerErrorKind,
     line: usize,
+    column: usize,
     message: String,
 }
 
+impl Diagnostic for ScannerError {
+    fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    fn line(&self) -> usize {
+        self.line
+    }
+
+    fn column(&self) -> usize {
+        self.column
+    }
+
+    fn message(&self) -> &str {
+        &self.message
+    }
+}
+
 enum ScanResult {
     Skip,
     Error(ScannerError),
@@ -45,42 +120,191 @@ pub struct Scanner<'source> {
     source: &'source str,
     tokens: Vec<Token>,
 
-    /// Current character in the lexeme being scanned
+    /// Byte offset of the current character in the lexeme being scanned
     current: usize,
     /// Line number of the current lexeme
     line: usize,
+    /// Column of the first character of the current lexeme
+    column: usize,
+    /// Line and column of the line terminator that was just skipped, if
+    /// nothing has been scanned since
+    line_end: Option<(usize, usize)>,
+
+    options: ScannerOptions,
+}
+
+/// Everything about how a Scanner goes about its business, for when the same
+/// configuration is used for several sources.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScannerOptions {
+    /// Keep comments as Comment tokens, whose literal is the text between the
+    /// comment delimiters. These are for tools like formatters; the parser
+    /// doesn't expect them.
+    pub preserve_comments: bool,
+    /// Number lines starting from this one instead of 1, for sources that
+    /// continue where an earlier one left off.
+    pub first_line: usize,
+    /// Count each tab as this many columns, so that reported columns line up
+    /// with an editor's.
+    pub tab_width: usize,
+    /// Refuse to scan sources longer than this many bytes, which are more
+    /// likely to be the wrong file than a program
+    pub max_source_len: Option<usize>,
+    /// Give up after this many errors rather than report every character of
+    /// a binary file
+    pub max_errors: Option<usize>,
+    /// Skip a byte order mark at the start of the source, since some editors
+    /// like to add one. Sources that continue an earlier one turn this off,
+    /// as a byte order mark anywhere but the start of a file is an error.
+    pub skip_bom: bool,
+}
+
+/// Default for `ScannerOptions::max_source_len`
+pub const DEFAULT_MAX_SOURCE_LEN: usize = 64 * 1024 * 1024;
+
+/// Default for `ScannerOptions::max_errors`
+pub const DEFAULT_MAX_ERRORS: usize = 25;
+
+impl Default for ScannerOptions {
+    fn default() -> Self {
+        ScannerOptions {
+            preserve_comments: false,
+            first_line: 1,
+            tab_width: 1,
+            max_source_len: Some(DEFAULT_MAX_SOURCE_LEN),
+            max_errors: Some(DEFAULT_MAX_ERRORS),
+            skip_bom: true,
+        }
+    }
 }
 
 impl<'source> Scanner<'source> {
+    /// Scan with the default options
     pub fn new(source: &'source str) -> Self {
+        Scanner::with_options(source, ScannerOptions::default())
+    }
+
+    /// Scan with the given options
+    pub fn with_options(source: &'source str, options: ScannerOptions) -> Self {
+        let source = match options.skip_bom {
+            true => source.strip_prefix('\u{FEFF}').unwrap_or(source),
+            false => source,
+        };
         Scanner {
             source,
             tokens: Vec::<Token>::new(),
             current: 0,
-            line: 1,
+            line: options.first_line,
+            column: 1,
+            line_end: None,
+            options,
         }
     }
 
+    /// Shorthand for setting `ScannerOptions::preserve_comments`
+    pub fn preserve_comments(mut self, preserve_comments: bool) -> Self {
+        self.options.preserve_comments = preserve_comments;
+        self
+    }
+
+    /// Shorthand for setting `ScannerOptions::first_line`
+    pub fn first_line(mut self, first_line: usize) -> Self {
+        self.options.first_line = first_line;
+        self.line = first_line;
+        self
+    }
+
+    /// Shorthand for setting `ScannerOptions::tab_width`
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.options.tab_width = tab_width;
+        self
+    }
+
     pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<ScannerError>> {
+        let (tokens, errors) = self.scan_tokens_lossy();
+
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Scan the entire source, skipping over anything that fails to scan, and
+    /// return whatever tokens were produced alongside the errors encountered.
+    /// Once there are too many errors, the last one says so and the rest of
+    /// the source is skipped.
+    pub fn scan_tokens_lossy(&mut self) -> (Vec<Token>, Vec<ScannerError>) {
         let mut errors = Vec::<ScannerError>::new();
 
+        while let Some(result) = self.scan_one() {
+            match result {
+                Err(error) if Some(errors.len()) == self.options.max_errors => {
+                    errors.push(ScannerError {
+                        kind: ScannerErrorKind::TooManyErrors,
+                        message: "too many errors; stopping".into(),
+                        ..error
+                    });
+                    self.current = self.source.len();
+                }
+                Err(error) => errors.push(error),
+                Ok(_) => continue,
+            }
+        }
+
+        (self.tokens.clone(), errors)
+    }
+
+    /// Scan up to and including the next token, skipping whitespace and
+    /// comments that aren't being preserved. The last token is always Eof,
+    /// after which this returns None.
+    pub fn scan_one(&mut self) -> Option<Result<Token, ScannerError>> {
+        if matches!(self.tokens.last(), Some(token) if token.token_type == TokenType::Eof) {
+            return None;
+        }
+
+        if let Some(max_source_len) = self.options.max_source_len {
+            if self.tokens.is_empty() && self.source.len() > max_source_len {
+                // Skip straight to the end
+                self.source = &self.source[self.source.len()..];
+                return Some(Err(ScannerError {
+                    kind: ScannerErrorKind::SourceTooLarge,
+                    line: self.line,
+                    column: self.column,
+                    message: "Source exceeds maximum size".into(),
+                }));
+            }
+        }
+
         while !self.is_at_end() {
-            self.source = &self.source[self.current..];
-            self.current = 0;
+            self.start_lexeme();
+            self.line_end = None;
             match self.scan_token() {
                 ScanResult::Skip => continue,
-                ScanResult::Error(error) => errors.push(error),
-                ScanResult::Token(token) => self.tokens.push(token),
+                ScanResult::Error(error) => return Some(Err(error)),
+                ScanResult::Token(token) => {
+                    self.tokens.push(token.clone());
+                    return Some(Ok(token));
+                }
             }
         }
 
-        self.tokens.push(self.new_token(TokenType::Eof));
+        self.start_lexeme();
+        // A final line terminator ends the last line rather than starting a
+        // new, empty one, so Eof goes at the end of that last line.
+        let eof = match self.line_end {
+            Some((line, column)) => Token::new(TokenType::Eof, "", line, column),
+            None => self.new_token(TokenType::Eof),
+        };
+        self.tokens.push(eof.clone());
+        Some(Ok(eof))
+    }
 
-        if errors.is_empty() {
-            Ok(self.tokens.clone())
-        } else {
-            Err(errors)
-        }
+    /// Move past the previous lexeme to start a new one
+    fn start_lexeme(&mut self) {
+        self.column = self.next_column();
+        self.source = &self.source[self.current..];
+        self.current = 0;
     }
 
     fn is_at_end(&self) -> bool {
@@ -88,13 +312,9 @@ impl<'source> Scanner<'source> {
     }
 
     fn advance(&mut self) -> Option<char> {
-        match self.source.chars().nth(self.current) {
-            Some(c) => {
-                self.current += 1;
-                Some(c)
-            }
-            None => None,
-        }
+        let c = self.peek()?;
+        self.current += c.len_utf8();
+        Some(c)
     }
 
     /// View the next character
@@ -103,69 +323,192 @@ impl<'source> Scanner<'source> {
     }
 
     fn peek_n(&self, n: usize) -> Option<char> {
-        self.source.chars().nth(self.current + n)
+        self.source[self.current..].chars().nth(n)
     }
 
     /// Consume the next character iff it matches expected
     fn match_next(&mut self, expected: char) -> bool {
-        if Some(expected) == self.source.chars().nth(self.current) {
-            self.current += 1;
+        if Some(expected) == self.peek() {
+            self.current += expected.len_utf8();
             return true;
         }
         false
     }
 
-    fn block_comment(&mut self) -> Result<(), ScannerError> {
+    /// Turn the current lexeme into a comment token, or skip it if comments
+    /// aren't being preserved.
+    fn comment(&self, body: &str) -> ScanResult {
+        if self.options.preserve_comments {
+            ScanResult::Token(self.new_literal_token(TokenType::Comment, body.into()))
+        } else {
+            ScanResult::Skip
+        }
+    }
+
+    fn line_comment(&mut self) -> ScanResult {
+        while !matches!(self.peek(), Some('\n' | '\r')) && !self.is_at_end() {
+            self.advance();
+        }
+        self.comment(&self.lexeme()[2..])
+    }
+
+    /// `#line N` or `#line N "file"` renumbers the lines that follow it, so
+    /// that code generated from another source reports positions in that
+    /// source. File names are accepted for the benefit of generators that
+    /// always write them, but tokens don't record a file.
+    fn line_directive(&mut self) -> ScanResult {
+        while !matches!(self.peek(), Some('\n' | '\r')) && !self.is_at_end() {
+            self.advance();
+        }
+        match parse_line_directive(&self.lexeme()[1..]) {
+            Some(line) => {
+                // The line ending the directive moves on to the given line
+                self.line = line - 1;
+                ScanResult::Skip
+            }
+            None => ScanResult::Error(ScannerError {
+                kind: ScannerErrorKind::MalformedLineDirective,
+                line: self.line,
+                column: self.column,
+                message: "Malformed directive, expected #line N or #line N \"file\"".into(),
+            }),
+        }
+    }
+
+    fn block_comment(&mut self) -> ScanResult {
         let mut line = self.line;
 
         while let Some(c) = self.advance() {
             match c {
                 '*' if self.match_next('/') => {
+                    let lexeme = self.lexeme();
+                    let result = self.comment(&lexeme[2..lexeme.len() - 2]);
                     self.line = line;
-                    return Ok(());
+                    return result;
                 }
                 '\n' => line += 1,
+                '\r' => {
+                    self.match_next('\n');
+                    line += 1;
+                }
                 _ => continue,
             }
         }
 
         let result = ScannerError {
+            kind: ScannerErrorKind::UnterminatedBlockComment,
             line: self.line,
+            column: self.column,
             message: "Unterminated block comment".into(),
         };
         self.line = line;
-        Err(result)
+        ScanResult::Error(result)
     }
 
-    fn string(&mut self) -> Result<Token, ScannerError> {
+    fn string(&mut self, raw: bool) -> Result<Token, ScannerError> {
         let mut line = self.line;
+        let mut value = String::new();
+        // A bad escape doesn't end the string, so that scanning carries on
+        // after it rather than in the middle of it
+        let mut invalid_escape = None;
 
         // TODO: this can probably be ... more concise
         while let Some(c) = self.advance() {
             match c {
                 '"' => {
-                    let lexeme = self.lexeme();
-                    let result = Ok(self
-                        .new_literal_token(TokenType::String, lexeme[1..lexeme.len() - 1].into()));
+                    let result = match invalid_escape {
+                        Some(error) => Err(error),
+                        None => {
+                            Ok(self.new_literal_token(TokenType::String, Literal::String(value)))
+                        }
+                    };
                     self.line = line;
                     return result;
                 }
+                '\\' if !raw && self.match_next('x') => match self.hex_escape() {
+                    Some(c) => value.push(c),
+                    None => {
+                        invalid_escape.get_or_insert(ScannerError {
+                            kind: ScannerErrorKind::InvalidEscape,
+                            line,
+                            column: self.column,
+                            message: "Invalid escape, expected \\x00 to \\x7F".into(),
+                        });
+                    }
+                },
+                // As in C, a backslash right before the end of a line joins
+                // it to the next one
+                '\\' if !raw && self.match_next('\n') => line += 1,
+                '\\' if !raw && self.match_next('\r') => {
+                    self.match_next('\n');
+                    line += 1;
+                }
                 '\n' => {
+                    value.push(c);
                     line += 1;
                 }
-                _ => continue,
+                '\r' => {
+                    value.push(c);
+                    if self.match_next('\n') {
+                        value.push('\n');
+                    }
+                    line += 1;
+                }
+                _ => value.push(c),
             }
         }
 
         let result = ScannerError {
+            kind: ScannerErrorKind::UnterminatedString,
             line: self.line,
+            column: self.column,
             message: "Unterminated string".into(),
         };
         self.line = line;
         Err(result)
     }
 
-    fn number(&mut self) -> Token {
+    /// The character written as two hex digits after `\x`. Anything past
+    /// 0x7F would be half of a UTF-8 sequence, so only ASCII is allowed.
+    fn hex_escape(&mut self) -> Option<char> {
+        let mut byte = 0;
+        for _ in 0..2 {
+            let digit = self.peek().and_then(|c| c.to_digit(16))?;
+            self.advance();
+            byte = byte * 16 + digit;
+        }
+        char::from_u32(byte).filter(char::is_ascii)
+    }
+
+    /// A character literal, which has to be exactly one character between
+    /// single quotes on the same line. There are no escapes.
+    fn char(&mut self) -> Result<Token, ScannerError> {
+        while !matches!(self.peek(), Some('\'' | '\n' | '\r') | None) {
+            self.advance();
+        }
+        if !self.match_next('\'') {
+            return Err(ScannerError {
+                kind: ScannerErrorKind::UnterminatedChar,
+                line: self.line,
+                column: self.column,
+                message: "Unterminated character literal".into(),
+            });
+        }
+
+        let lexeme = self.lexeme();
+        let mut chars = lexeme[1..lexeme.len() - 1].chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(self.new_literal_token(TokenType::Char, Literal::Char(c))),
+            _ => Err(ScannerError {
+                kind: ScannerErrorKind::InvalidChar,
+                line: self.line,
+                column: self.column,
+                message: "Character literal must be exactly one character".into(),
+            }),
+        }
+    }
+
+    fn number(&mut self) -> Result<Token, ScannerError> {
         while let Some(c) = self.peek() {
             if c.is_ascii_digit() {
                 self.advance();
@@ -190,10 +533,15 @@ impl<'source> Scanner<'source> {
             }
         }
 
-        self.new_literal_token(
-            TokenType::Number,
-            Literal::Number(self.lexeme().parse().unwrap()),
-        )
+        match self.lexeme().parse() {
+            Ok(number) => Ok(self.new_literal_token(TokenType::Number, Literal::Number(number))),
+            Err(_) => Err(ScannerError {
+                kind: ScannerErrorKind::InvalidNumber,
+                line: self.line,
+                column: self.column,
+                message: "Invalid number literal".into(),
+            }),
+        }
     }
 
     fn identifier(&mut self) -> Token {
@@ -225,7 +573,9 @@ impl<'source> Scanner<'source> {
 
         match self.advance() {
             None => Error(ScannerError {
+                kind: ScannerErrorKind::ExpectedToken,
                 line: self.line,
+                column: self.column,
                 message: "Expected token".into(),
             }),
             Some('(') => Token(self.new_token(TokenType::LeftParen)),
@@ -238,42 +588,63 @@ impl<'source> Scanner<'source> {
             Some('+') => Token(self.new_token(TokenType::Plus)),
             Some(';') => Token(self.new_token(TokenType::Semicolon)),
             Some('*') => Token(self.new_token(TokenType::Star)),
+            Some('%') => Token(self.new_token(TokenType::Percent)),
+            Some('?') if self.match_next('?') => Token(self.new_token(TokenType::InterroInterro)),
             Some('?') => Token(self.new_token(TokenType::Interro)),
             Some(':') => Token(self.new_token(TokenType::Colon)),
+            Some('&') => Token(self.new_token(TokenType::Ampersand)),
+            Some('|') => Token(self.new_token(TokenType::Pipe)),
+            Some('^') => Token(self.new_token(TokenType::Caret)),
             Some('!') if self.match_next('=') => Token(self.new_token(TokenType::BangEqual)),
             Some('=') if self.match_next('=') => Token(self.new_token(TokenType::EqualEqual)),
             Some('<') if self.match_next('=') => Token(self.new_token(TokenType::LessEqual)),
             Some('>') if self.match_next('=') => Token(self.new_token(TokenType::GreaterEqual)),
+            Some('<') if self.match_next('<') => Token(self.new_token(TokenType::LessLess)),
+            Some('>') if self.match_next('>') => Token(self.new_token(TokenType::GreaterGreater)),
             Some('!') => Token(self.new_token(TokenType::Bang)),
             Some('=') => Token(self.new_token(TokenType::Equal)),
             Some('<') => Token(self.new_token(TokenType::Less)),
             Some('>') => Token(self.new_token(TokenType::Greater)),
-            Some('/') if self.match_next('/') => {
-                while self.peek() != Some('\n') && !self.is_at_end() {
-                    self.advance();
-                }
-                Skip
-            }
-            Some('/') if self.match_next('*') => match self.block_comment() {
-                Ok(_) => Skip,
-                Err(error) => Error(error),
-            },
+            Some('/') if self.match_next('/') => self.line_comment(),
+            Some('/') if self.match_next('*') => self.block_comment(),
             Some('/') => Token(self.new_token(TokenType::Slash)),
             Some(' ') => Skip,
             Some('\t') => Skip,
-            Some('\r') => Skip,
+            // \r\n and a bare \r (old Macs) are both a single newline
+            Some('\r') => {
+                self.match_next('\n');
+                self.line_end = Some((self.line, self.column));
+                self.line += 1;
+                Skip
+            }
             Some('\n') => {
+                self.line_end = Some((self.line, self.column));
                 self.line += 1;
                 Skip
             }
-            Some('"') => match self.string() {
+            Some('#') if self.column == 1 => self.line_directive(),
+            // Raw strings, where a backslash is just a backslash
+            Some('r') if self.match_next('"') => match self.string(true) {
+                Ok(token) => Token(token),
+                Err(error) => Error(error),
+            },
+            Some('"') => match self.string(false) {
+                Ok(token) => Token(token),
+                Err(error) => Error(error),
+            },
+            Some('\'') => match self.char() {
+                Ok(token) => Token(token),
+                Err(error) => Error(error),
+            },
+            Some(c) if c.is_ascii_digit() => match self.number() {
                 Ok(token) => Token(token),
                 Err(error) => Error(error),
             },
-            Some(c) if c.is_ascii_digit() => Token(self.number()),
             Some(c) if c.is_alphabetic() || c == '_' => Token(self.identifier()),
             Some(c) => Error(ScannerError {
+                kind: ScannerErrorKind::UnexpectedCharacter,
                 line: self.line,
+                column: self.column,
                 message: format!("Unexpected character {}", c),
             }),
         }
@@ -283,12 +654,212 @@ impl<'source> Scanner<'source> {
         &self.source[..self.current]
     }
 
+    /// Column of the character following the current lexeme
+    fn next_column(&self) -> usize {
+        self.lexeme()
+            .chars()
+            .fold(self.column, |column, c| match c {
+                '\n' | '\r' => 1,
+                '\t' => column + self.options.tab_width,
+                _ => column + 1,
+            })
+    }
+
     fn new_token(&self, token_type: TokenType) -> Token {
-        Token::new(token_type, self.lexeme(), self.line)
+        Token::new(token_type, self.lexeme(), self.line, self.column)
     }
 
     fn new_literal_token(&self, token_type: TokenType, literal: Literal) -> Token {
-        Token::new_literal(token_type, self.lexeme(), literal, self.line)
+        Token::new_literal(token_type, self.lexeme(), literal, self.line, self.column)
+    }
+}
+
+/// Scanner that reads its source a line at a time as tokens are asked for,
+/// rather than needing all of it in memory. It produces the same tokens and
+/// errors as `Scanner::scan_one`, except that a source that grows too large
+/// or has too many errors is cut off where that happens rather than at the
+/// start, and failing to read the source is an error too.
+pub struct ReadScanner<R: BufRead> {
+    reader: R,
+    options: ScannerOptions,
+    /// Tokens and errors scanned but not yet returned
+    pending: VecDeque<Result<Token, ScannerError>>,
+    /// Where the next line starts
+    line: usize,
+    /// Eof of the lines read so far, to be returned once nothing follows
+    eof: Option<Token>,
+    bytes_read: usize,
+    error_count: usize,
+    /// Whether no line has been scanned yet, so that only the first one can
+    /// start with a byte order mark
+    first_chunk: bool,
+    done: bool,
+}
+
+impl<R: BufRead> ReadScanner<R> {
+    pub fn new(reader: R, options: ScannerOptions) -> Self {
+        ReadScanner {
+            reader,
+            line: options.first_line,
+            options,
+            pending: VecDeque::new(),
+            eof: None,
+            bytes_read: 0,
+            error_count: 0,
+            first_chunk: true,
+            done: false,
+        }
+    }
+
+    /// Scan the next line, or as many as it takes to finish a string or
+    /// comment that spans several
+    fn scan_lines(&mut self) {
+        let mut chunk = String::new();
+        loop {
+            let at_end = match self.reader.read_line(&mut chunk) {
+                Ok(bytes) => bytes == 0,
+                Err(error) => {
+                    let message = format!("Failed to read source: {}", error);
+                    return self.stop(ScannerErrorKind::ReadFailed, message, self.line, 1);
+                }
+            };
+            self.bytes_read += chunk.len();
+            if matches!(self.options.max_source_len, Some(max) if self.bytes_read > max) {
+                let message = "Source exceeds maximum size".into();
+                return self.stop(ScannerErrorKind::SourceTooLarge, message, self.line, 1);
+            }
+            if at_end && chunk.is_empty() {
+                self.eof
+                    .get_or_insert_with(|| Token::new(TokenType::Eof, "", self.line, 1));
+                self.done = true;
+                return;
+            }
+
+            let options = ScannerOptions {
+                first_line: self.line,
+                max_source_len: None,
+                max_errors: None,
+                skip_bom: self.options.skip_bom && self.first_chunk,
+                ..self.options.clone()
+            };
+            let mut scanner = Scanner::with_options(&chunk, options);
+            let results: Vec<_> = std::iter::from_fn(|| scanner.scan_one()).collect();
+
+            // An unterminated string or comment is the last thing before Eof
+            let unfinished = matches!(
+                results.iter().rev().nth(1),
+                Some(Err(ScannerError {
+                    kind: ScannerErrorKind::UnterminatedString
+                        | ScannerErrorKind::UnterminatedBlockComment,
+                    ..
+                }))
+            );
+            if unfinished && !at_end {
+                // Scan it again with the next line added
+                continue;
+            }
+
+            self.line = scanner.line;
+            self.first_chunk = false;
+            for result in results {
+                match result {
+                    Ok(token) if token.token_type == TokenType::Eof => self.eof = Some(token),
+                    Ok(token) => self.pending.push_back(Ok(token)),
+                    Err(error) if Some(self.error_count) == self.options.max_errors => {
+                        let message = "too many errors; stopping".into();
+                        return self.stop(
+                            ScannerErrorKind::TooManyErrors,
+                            message,
+                            error.line,
+                            error.column,
+                        );
+                    }
+                    Err(error) => {
+                        self.error_count += 1;
+                        self.pending.push_back(Err(error));
+                    }
+                }
+            }
+            self.done = at_end;
+            return;
+        }
+    }
+
+    /// Give up on the rest of the source with an error, ending there
+    fn stop(&mut self, kind: ScannerErrorKind, message: String, line: usize, column: usize) {
+        self.pending.push_back(Err(ScannerError {
+            kind,
+            line,
+            column,
+            message,
+        }));
+        self.eof = Some(Token::new(TokenType::Eof, "", line, column));
+        self.done = true;
+    }
+}
+
+impl<R: BufRead> Iterator for ReadScanner<R> {
+    type Item = Result<Token, ScannerError>;
+
+    /// The next token or error, as for `Scanner::scan_one`
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.pending.pop_front() {
+                return Some(result);
+            }
+            if self.done {
+                return self.eof.take().map(Ok);
+            }
+            self.scan_lines();
+        }
+    }
+}
+
+/// The text of a line of the source, numbered from 1, without its line
+/// terminator. Lines end where the scanner ends them, at `\n`, `\r\n` or a
+/// bare `\r`, and a leading byte order mark is skipped as by the scanner.
+/// `#line` directives aren't taken into account.
+pub fn source_line(source: &str, line: usize) -> Option<&str> {
+    let mut rest = source.strip_prefix('\u{FEFF}').unwrap_or(source);
+    for _ in 1..line {
+        let end = rest.find(['\n', '\r'])?;
+        let terminator = if rest[end..].starts_with("\r\n") { 2 } else { 1 };
+        rest = &rest[end + terminator..];
+    }
+    // Nothing after the last line terminator doesn't make another line
+    if line == 0 || rest.is_empty() {
+        return None;
+    }
+    Some(&rest[..rest.find(['\n', '\r']).unwrap_or(rest.len())])
+}
+
+/// The line number from the text of a line directive after the `#`, if it is
+/// well-formed
+fn parse_line_directive(directive: &str) -> Option<usize> {
+    let rest = directive.strip_prefix("line")?;
+    if !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let line = rest[..digits]
+        .parse::<usize>()
+        .ok()
+        .filter(|line| *line > 0)?;
+
+    let file = &rest[digits..];
+    let is_file_name = |file: &str| {
+        file.len() >= 2
+            && file.starts_with('"')
+            && file.ends_with('"')
+            && !file[1..file.len() - 1].contains('"')
+    };
+    if file.trim().is_empty() || (file.starts_with([' ', '\t']) && is_file_name(file.trim())) {
+        Some(line)
+    } else {
+        None
     }
 }
 
@@ -300,29 +871,473 @@ mod test {
     fn tokenize_singles() -> Result<(), Vec<ScannerError>> {
         let mut under_test = Scanner::new("(}-");
         let tokens = under_test.scan_tokens()?;
-        assert!(tokens.contains(&Token::new(TokenType::LeftParen, "(", 1)));
-        assert!(tokens.contains(&Token::new(TokenType::RightBrace, "}", 1)));
-        assert!(tokens.contains(&Token::new(TokenType::Minus, "-", 1)));
+        assert!(tokens.contains(&Token::new(TokenType::LeftParen, "(", 1, 1)));
+        assert!(tokens.contains(&Token::new(TokenType::RightBrace, "}", 1, 2)));
+        assert!(tokens.contains(&Token::new(TokenType::Minus, "-", 1, 3)));
         Ok(())
     }
 
     #[test]
     fn tokenize_unknown_char() {
-        let mut under_test = Scanner::new("%(}-+&+");
+        let mut under_test = Scanner::new("@(}-+#+");
         let tokens = under_test.scan_tokens();
         assert!(tokens.is_err());
         let errors = tokens.unwrap_err();
-        assert_eq!(errors[0].message, "Unexpected character %");
-        assert_eq!(errors[1].message, "Unexpected character &");
+        assert_eq!(errors[0].message, "Unexpected character @");
+        assert_eq!(errors[1].message, "Unexpected character #");
+    }
+
+    #[test]
+    fn tokenize_run_of_unknown_chars() {
+        let mut under_test = Scanner::new("@@@");
+        let errors = under_test.scan_tokens().unwrap_err();
+        let columns: Vec<usize> = errors.iter().map(|e| e.column).collect();
+        assert_eq!(columns, [1, 2, 3]);
+        assert!(errors.iter().all(|e| e.message == "Unexpected character @"));
+    }
+
+    #[test]
+    fn tokenize_unknown_chars_between_identifiers() {
+        let mut under_test = Scanner::new("a@@b");
+        let (tokens, errors) = under_test.scan_tokens_lossy();
+        assert_eq!(
+            tokens[..2],
+            [
+                Token::new(TokenType::Identifier, "a", 1, 1),
+                Token::new(TokenType::Identifier, "b", 1, 4),
+            ]
+        );
+        let columns: Vec<usize> = errors.iter().map(|e| e.column).collect();
+        assert_eq!(columns, [2, 3]);
+    }
+
+    #[test]
+    fn tokenize_with_tab_width() -> Result<(), Vec<ScannerError>> {
+        let tokens = Scanner::new("\t+").scan_tokens()?;
+        assert_eq!(tokens[0].column, 2);
+
+        let tokens = Scanner::new("\t+").tab_width(4).scan_tokens()?;
+        assert_eq!(tokens[0].column, 5);
+
+        let tokens = Scanner::new("1\n\t\t+").tab_width(4).scan_tokens()?;
+        assert_eq!(tokens[1].column, 9);
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_lossy() {
+        let mut under_test = Scanner::new("1 @ 2");
+        let (tokens, errors) = under_test.scan_tokens_lossy();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unexpected character @");
+        let token_types: Vec<TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            token_types,
+            [TokenType::Number, TokenType::Number, TokenType::Eof]
+        );
+    }
+
+    #[test]
+    fn scan_one_at_a_time() {
+        let mut under_test = Scanner::new("1 + // one\n2");
+        let mut next = || under_test.scan_one().map(Result::unwrap);
+
+        assert_eq!(
+            next(),
+            Some(Token::new_literal(
+                TokenType::Number,
+                "1",
+                Literal::Number(1.0),
+                1,
+                1
+            ))
+        );
+        assert_eq!(next(), Some(Token::new(TokenType::Plus, "+", 1, 3)));
+        assert_eq!(
+            next(),
+            Some(Token::new_literal(
+                TokenType::Number,
+                "2",
+                Literal::Number(2.0),
+                2,
+                1
+            ))
+        );
+        assert_eq!(next(), Some(Token::new(TokenType::Eof, "", 2, 2)));
+        assert_eq!(next(), None);
+        assert_eq!(next(), None);
+    }
+
+    #[test]
+    fn eof_line() -> Result<(), Vec<ScannerError>> {
+        let eof = |source| -> Result<Token, Vec<ScannerError>> {
+            Ok(Scanner::new(source).scan_tokens()?.pop().unwrap())
+        };
+        assert_eq!(
+            eof("print 1;\nprint 2;")?,
+            Token::new(TokenType::Eof, "", 2, 9)
+        );
+        assert_eq!(
+            eof("print 1;\nprint 2;\n")?,
+            Token::new(TokenType::Eof, "", 2, 9)
+        );
+        assert_eq!(eof("print 1;\r\n")?, Token::new(TokenType::Eof, "", 1, 9));
+        // Anything after the final newline, even blank lines, is a line of its
+        // own
+        assert_eq!(eof("1\n\n")?, Token::new(TokenType::Eof, "", 2, 1));
+        assert_eq!(eof("1\n  ")?, Token::new(TokenType::Eof, "", 2, 3));
+        assert_eq!(eof("")?, Token::new(TokenType::Eof, "", 1, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn line_directive() -> Result<(), Vec<ScannerError>> {
+        let tokens = Scanner::new("a\n#line 100\nb\nc\n#line 7 \"gen.y\"\r\nd").scan_tokens()?;
+        let lines: Vec<usize> = tokens.iter().map(|t| t.line).collect();
+        assert_eq!(lines, [1, 100, 101, 7, 7]);
+        assert_eq!(tokens[1], Token::new(TokenType::Identifier, "b", 100, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_line_directive() {
+        for source in [
+            "#line",
+            "#line x",
+            "#line 0",
+            "#line 10x",
+            "#line 10 gen.y",
+            "#line 10 \"gen.y",
+            "#lines 10",
+            "#define X 1",
+        ] {
+            let errors = Scanner::new(source).scan_tokens().unwrap_err();
+            assert_eq!(errors.len(), 1, "{}", source);
+            assert_eq!(errors[0].kind, ScannerErrorKind::MalformedLineDirective);
+        }
+
+        // Only at the start of a line
+        let errors = Scanner::new("1 #line 10").scan_tokens().unwrap_err();
+        assert_eq!(errors[0].kind, ScannerErrorKind::UnexpectedCharacter);
+    }
+
+    #[test]
+    fn string_line_continuation() -> Result<(), Vec<ScannerError>> {
+        let tokens = Scanner::new("\"one \\\ntwo\" \"a\\\r\nb\\c\"\nx").scan_tokens()?;
+        assert_eq!(tokens[0].literal, Some(Literal::from("one two")));
+        assert_eq!(tokens[0].line, 1);
+        assert_eq!(tokens[1].literal, Some(Literal::from("ab\\c")));
+        assert_eq!(tokens[1].line, 2);
+        assert_eq!(tokens[2], Token::new(TokenType::Identifier, "x", 4, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn raw_strings() -> Result<(), Vec<ScannerError>> {
+        let tokens =
+            Scanner::new("r\"C:\\path\\no\\escapes\\\" r \"a\\\nb\" r\"c\\\nd\"").scan_tokens()?;
+        assert_eq!(
+            tokens[0],
+            Token::new_literal(
+                TokenType::String,
+                "r\"C:\\path\\no\\escapes\\\"",
+                Literal::from("C:\\path\\no\\escapes\\"),
+                1,
+                1
+            )
+        );
+        // Only directly before the quote does the r make a raw string
+        assert_eq!(tokens[1], Token::new(TokenType::Identifier, "r", 1, 24));
+        assert_eq!(tokens[2].literal, Some(Literal::from("ab")));
+        assert_eq!(tokens[3].literal, Some(Literal::from("c\\\nd")));
+        assert_eq!(tokens[3].line, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn hex_escapes() -> Result<(), Vec<ScannerError>> {
+        let tokens = Scanner::new(r#""\x41\x62c\x7f" r"\x41""#).scan_tokens()?;
+        assert_eq!(tokens[0].literal, Some(Literal::from("Abc\x7f")));
+        assert_eq!(tokens[1].literal, Some(Literal::from("\\x41")));
+
+        for source in [r#""\xZZ""#, r#""\x4""#, r#""\x80""#, r#""\xff""#] {
+            let errors = Scanner::new(source).scan_tokens().unwrap_err();
+            assert_eq!(errors.len(), 1, "{}", source);
+            assert_eq!(errors[0].kind, ScannerErrorKind::InvalidEscape);
+        }
+
+        // The rest of the string is skipped rather than scanned as code
+        let (tokens, errors) = Scanner::new("\"\\xZZ + 1\" + 2").scan_tokens_lossy();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            tokens.iter().map(|t| t.lexeme.as_str()).collect::<Vec<_>>(),
+            ["+", "2", ""]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn char_literals() -> Result<(), Vec<ScannerError>> {
+        let tokens = Scanner::new("'x' + '\u{e9}'").scan_tokens()?;
+        assert_eq!(
+            tokens[0],
+            Token::new_literal(TokenType::Char, "'x'", Literal::Char('x'), 1, 1)
+        );
+        assert_eq!(tokens[2].literal, Some(Literal::Char('\u{e9}')));
+
+        for source in ["'ab'", "''"] {
+            let errors = Scanner::new(source).scan_tokens().unwrap_err();
+            assert_eq!(errors.len(), 1, "{}", source);
+            assert_eq!(errors[0].kind, ScannerErrorKind::InvalidChar);
+        }
+
+        // An unterminated literal ends with the line, and scanning carries on
+        // on the next one
+        let (tokens, errors) = Scanner::new("1 + 'a\n2").scan_tokens_lossy();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ScannerErrorKind::UnterminatedChar);
+        assert_eq!((errors[0].line, errors[0].column), (1, 5));
+        assert_eq!(
+            tokens.iter().map(|t| t.lexeme.as_str()).collect::<Vec<_>>(),
+            ["1", "+", "2", ""]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn source_lines() {
+        let source = "first\nmiddle\r\n\rlast";
+        assert_eq!(source_line(source, 1), Some("first"));
+        assert_eq!(source_line(source, 2), Some("middle"));
+        assert_eq!(source_line(source, 3), Some(""));
+        assert_eq!(source_line(source, 4), Some("last"));
+        assert_eq!(source_line(source, 5), None);
+        assert_eq!(source_line(source, 0), None);
+
+        assert_eq!(source_line("\u{FEFF}1 + 2\n", 1), Some("1 + 2"));
+        assert_eq!(source_line("1 + 2\n", 2), None);
+        assert_eq!(source_line("", 1), None);
+    }
+
+    #[test]
+    fn read_scanner() {
+        let source = "print \"multi\nline\";\n/* a\n comment */ 1 +\n\t2 @ 3;\n\"unterminated";
+        let mut scanner = Scanner::new(source);
+        let in_memory: Vec<_> = std::iter::from_fn(|| scanner.scan_one()).collect();
+        let streamed: Vec<_> =
+            ReadScanner::new(source.as_bytes(), ScannerOptions::default()).collect();
+
+        assert_eq!(format!("{:?}", streamed), format!("{:?}", in_memory));
+        assert_eq!(streamed.len(), 11);
+
+        for source in ["", "1\n", "1\n\n", "#line 10\nx"] {
+            let tokens = Scanner::new(source).scan_tokens().unwrap();
+            let streamed: Vec<Token> =
+                ReadScanner::new(source.as_bytes(), ScannerOptions::default())
+                    .map(Result::unwrap)
+                    .collect();
+            assert_eq!(streamed, tokens, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn read_scanner_limits() {
+        let options = ScannerOptions {
+            max_source_len: Some(8),
+            max_errors: Some(1),
+            ..Default::default()
+        };
+        let results: Vec<_> =
+            ReadScanner::new("1 + 2\n3 + 4\n5".as_bytes(), options.clone()).collect();
+        assert_eq!(results.len(), 5);
+        assert!(matches!(
+            &results[3],
+            Err(ScannerError {
+                kind: ScannerErrorKind::SourceTooLarge,
+                line: 2,
+                ..
+            })
+        ));
+
+        let results: Vec<_> = ReadScanner::new("@\n@".as_bytes(), options).collect();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(
+            &results[1],
+            Err(ScannerError {
+                kind: ScannerErrorKind::TooManyErrors,
+                line: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn scan_one_error() {
+        let mut under_test = Scanner::new("@1");
+        assert!(matches!(under_test.scan_one(), Some(Err(_))));
+        assert!(matches!(under_test.scan_one(), Some(Ok(token)) if token.lexeme == "1"));
+    }
+
+    #[test]
+    fn source_too_large() {
+        let options = ScannerOptions {
+            max_source_len: Some(5),
+            ..Default::default()
+        };
+        let (tokens, errors) = Scanner::with_options("1 + 23", options.clone()).scan_tokens_lossy();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ScannerErrorKind::SourceTooLarge);
+        assert_eq!(
+            errors[0].to_string(),
+            "E0003: 1:1: Source exceeds maximum size"
+        );
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token_type, TokenType::Eof);
+
+        assert!(Scanner::with_options("1 + 2", options)
+            .scan_tokens()
+            .is_ok());
+        let options = ScannerOptions {
+            max_source_len: None,
+            ..Default::default()
+        };
+        assert!(Scanner::with_options("1 + 23", options)
+            .scan_tokens()
+            .is_ok());
+    }
+
+    #[test]
+    fn skip_leading_bom() -> Result<(), Vec<ScannerError>> {
+        let tokens = Scanner::new("\u{FEFF}print 1;").scan_tokens()?;
+        assert_eq!(tokens[0], Token::new(TokenType::Print, "print", 1, 1));
+
+        let errors = Scanner::new("1 \u{FEFF}").scan_tokens().unwrap_err();
+        assert_eq!(errors[0].message, "Unexpected character \u{FEFF}");
+        assert_eq!(errors[0].column, 3);
+
+        let options = ScannerOptions {
+            skip_bom: false,
+            ..Default::default()
+        };
+        let errors = Scanner::with_options("\u{FEFF}print 1;", options)
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!(errors[0].message, "Unexpected character \u{FEFF}");
+
+        // Only the first of the lines read one at a time starts the source
+        let source = "\u{FEFF}print 1;\n\u{FEFF}print 2;";
+        let errors: Vec<_> = ReadScanner::new(source.as_bytes(), ScannerOptions::default())
+            .filter_map(Result::err)
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (2, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_non_ascii() -> Result<(), Vec<ScannerError>> {
+        let tokens = Scanner::new("\"héllo\" + ünï").scan_tokens()?;
+        assert_eq!(
+            tokens,
+            [
+                Token::new_literal(TokenType::String, "\"héllo\"", "héllo".into(), 1, 1),
+                Token::new(TokenType::Plus, "+", 1, 9),
+                Token::new(TokenType::Identifier, "ünï", 1, 11),
+                Token::new(TokenType::Eof, "", 1, 14),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn max_errors() {
+        let errors = Scanner::new(&"@".repeat(1000)).scan_tokens().unwrap_err();
+        assert_eq!(errors.len(), DEFAULT_MAX_ERRORS + 1);
+        assert!(errors[..DEFAULT_MAX_ERRORS]
+            .iter()
+            .all(|e| e.kind == ScannerErrorKind::UnexpectedCharacter));
+        assert_eq!(
+            errors[DEFAULT_MAX_ERRORS].kind,
+            ScannerErrorKind::TooManyErrors
+        );
+        assert_eq!(
+            errors[DEFAULT_MAX_ERRORS].message,
+            "too many errors; stopping"
+        );
+        assert_eq!(errors[DEFAULT_MAX_ERRORS].column, DEFAULT_MAX_ERRORS + 1);
+
+        let options = ScannerOptions {
+            max_errors: Some(3),
+            ..Default::default()
+        };
+        // Exactly as many errors as allowed is fine
+        let errors = Scanner::with_options("@@@", options.clone())
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!(errors.len(), 3);
+        let (tokens, errors) = Scanner::with_options("@@@@ 1", options).scan_tokens_lossy();
+        assert_eq!(errors.len(), 4);
+        assert_eq!(tokens.len(), 1);
+
+        let options = ScannerOptions {
+            max_errors: None,
+            ..Default::default()
+        };
+        let errors = Scanner::with_options(&"@".repeat(1000), options)
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!(errors.len(), 1000);
+    }
+
+    #[test]
+    fn tokenize_percent() -> Result<(), Vec<ScannerError>> {
+        let mut under_test = Scanner::new("7 % 3");
+        let tokens = under_test.scan_tokens()?;
+        assert_eq!(tokens[1], Token::new(TokenType::Percent, "%", 1, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_coalesce() -> Result<(), Vec<ScannerError>> {
+        let tokens = Scanner::new("a ?? b ? c").scan_tokens()?;
+        assert_eq!(tokens[1], Token::new(TokenType::InterroInterro, "??", 1, 3));
+        assert_eq!(tokens[3], Token::new(TokenType::Interro, "?", 1, 8));
+
+        let tokens = Scanner::new("???").scan_tokens()?;
+        assert_eq!(tokens[0].token_type, TokenType::InterroInterro);
+        assert_eq!(tokens[1].token_type, TokenType::Interro);
+        Ok(())
     }
 
     #[test]
     fn tokenize_two_char_ops() -> Result<(), Vec<ScannerError>> {
         let mut under_test = Scanner::new("!!=+");
         let tokens = under_test.scan_tokens()?;
-        assert!(tokens.contains(&Token::new(TokenType::Bang, "!", 1)));
-        assert!(tokens.contains(&Token::new(TokenType::BangEqual, "!=", 1)));
-        assert!(tokens.contains(&Token::new(TokenType::Plus, "+", 1)));
+        assert!(tokens.contains(&Token::new(TokenType::Bang, "!", 1, 1)));
+        assert!(tokens.contains(&Token::new(TokenType::BangEqual, "!=", 1, 2)));
+        assert!(tokens.contains(&Token::new(TokenType::Plus, "+", 1, 4)));
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_bitwise_ops() -> Result<(), Vec<ScannerError>> {
+        let tokens = Scanner::new("<<<=< >>>=>&|^").scan_tokens()?;
+        let token_types: Vec<TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            token_types,
+            [
+                TokenType::LessLess,
+                TokenType::LessEqual,
+                TokenType::Less,
+                TokenType::GreaterGreater,
+                TokenType::GreaterEqual,
+                TokenType::Greater,
+                TokenType::Ampersand,
+                TokenType::Pipe,
+                TokenType::Caret,
+                TokenType::Eof,
+            ]
+        );
         Ok(())
     }
 
@@ -330,8 +1345,8 @@ mod test {
     fn tokenize_comment_whitespace() -> Result<(), Vec<ScannerError>> {
         let mut under_test = Scanner::new("+// testing\n=");
         let tokens = under_test.scan_tokens()?;
-        assert!(tokens.contains(&Token::new(TokenType::Plus, "+", 1)));
-        assert!(tokens.contains(&Token::new(TokenType::Equal, "=", 2)));
+        assert!(tokens.contains(&Token::new(TokenType::Plus, "+", 1, 1)));
+        assert!(tokens.contains(&Token::new(TokenType::Equal, "=", 2, 1)));
         Ok(())
     }
 
@@ -343,8 +1358,58 @@ mod test {
             -"#,
         );
         let tokens = under_test.scan_tokens()?;
-        assert!(tokens.contains(&Token::new(TokenType::Plus, "+", 1)));
-        assert!(tokens.contains(&Token::new(TokenType::Minus, "-", 3)));
+        assert!(tokens.contains(&Token::new(TokenType::Plus, "+", 1, 1)));
+        assert!(tokens.contains(&Token::new(TokenType::Minus, "-", 3, 13)));
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_preserved_comments() -> Result<(), Vec<ScannerError>> {
+        let mut under_test = Scanner::new("a // hi\n b /* multi\nline */").preserve_comments(true);
+        let tokens = under_test.scan_tokens()?;
+        assert_eq!(
+            tokens[..4],
+            [
+                Token::new(TokenType::Identifier, "a", 1, 1),
+                Token::new_literal(TokenType::Comment, "// hi", " hi".into(), 1, 3),
+                Token::new(TokenType::Identifier, "b", 2, 2),
+                Token::new_literal(
+                    TokenType::Comment,
+                    "/* multi\nline */",
+                    " multi\nline ".into(),
+                    2,
+                    4
+                ),
+            ]
+        );
+        assert_eq!(tokens[4].token_type, TokenType::Eof);
+        Ok(())
+    }
+
+    #[test]
+    fn scan_with_options() -> Result<(), Vec<ScannerError>> {
+        let options = ScannerOptions {
+            preserve_comments: true,
+            first_line: 3,
+            ..Default::default()
+        };
+        let tokens = Scanner::with_options("// hi\n\t1", options.clone()).scan_tokens()?;
+        assert_eq!(
+            tokens[0],
+            Token::new_literal(TokenType::Comment, "// hi", " hi".into(), 3, 1)
+        );
+        assert_eq!(tokens[1].line, 4);
+        assert_eq!(tokens[1].column, 2);
+
+        // The options can be reused, and the shorthands do the same thing
+        let tokens = Scanner::with_options("1 // one", options).scan_tokens()?;
+        assert_eq!(tokens[1].token_type, TokenType::Comment);
+        let tokens = Scanner::new("// hi\n\t1")
+            .preserve_comments(true)
+            .first_line(3)
+            .scan_tokens()?;
+        assert_eq!(tokens[0].line, 3);
+        assert_eq!(tokens[1].line, 4);
         Ok(())
     }
 
@@ -359,9 +1424,22 @@ mod test {
             TokenType::String,
             "\"multiline\n+ tokens\"",
             "multiline\n+ tokens".into(),
+            1,
             1
         )));
-        assert!(tokens.contains(&Token::new(TokenType::Plus, "+", 2)));
+        assert!(tokens.contains(&Token::new(TokenType::Plus, "+", 2, 10)));
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_carriage_returns() -> Result<(), Vec<ScannerError>> {
+        for source in ["a\r\nb", "a\rb"] {
+            let tokens = Scanner::new(source).scan_tokens()?;
+            assert_eq!(tokens[1], Token::new(TokenType::Identifier, "b", 2, 1));
+        }
+
+        let tokens = Scanner::new("\"x\r\ny\"\r\n// c\r\nz").scan_tokens()?;
+        assert_eq!(tokens[1], Token::new(TokenType::Identifier, "z", 4, 1));
         Ok(())
     }
 
@@ -386,13 +1464,23 @@ mod test {
         test("0.6+", 0.6);
     }
 
+    #[test]
+    fn tokenize_malformed_number() {
+        // Scanning never produces a lexeme like this, so set one up directly
+        let mut under_test = Scanner::new("1.2.3");
+        under_test.current = 5;
+        let error = under_test.number().unwrap_err();
+        assert_eq!(error.kind, ScannerErrorKind::InvalidNumber);
+        assert_eq!(error.to_string(), "E0103: 1:1: Invalid number literal");
+    }
+
     #[test]
     fn tokenize_identifiers() -> Result<(), Vec<ScannerError>> {
         let mut under_test = Scanner::new("for class variable_name1");
         let tokens = under_test.scan_tokens()?;
-        assert!(tokens.contains(&Token::new(TokenType::For, "for", 1)));
-        assert!(tokens.contains(&Token::new(TokenType::Class, "class", 1)));
-        assert!(tokens.contains(&Token::new(TokenType::Identifier, "variable_name1", 1)));
+        assert!(tokens.contains(&Token::new(TokenType::For, "for", 1, 1)));
+        assert!(tokens.contains(&Token::new(TokenType::Class, "class", 1, 5)));
+        assert!(tokens.contains(&Token::new(TokenType::Identifier, "variable_name1", 1, 11)));
         Ok(())
     }
 }
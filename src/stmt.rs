@@ -0,0 +1,32 @@
+// generated by: cargo run --bin generate_ast src
+
+use crate::expr::Expr;
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stmt {
+    Expression {
+        expression: Box<Expr>,
+    },
+    Print {
+        expressions: Vec<Expr>,
+    },
+}
+impl Stmt {
+    pub fn accept<R>(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        match self {
+            Stmt::Expression { expression } => visitor.visit_expression(expression),
+            Stmt::Print { expressions } => visitor.visit_print(expressions),
+        }
+    }
+    pub fn new_expression(expression: Expr) -> Stmt {
+        Stmt::Expression { expression: Box::new(expression) }
+    }
+    pub fn new_print(expressions: Vec<Expr>) -> Stmt {
+        Stmt::Print { expressions }
+    }
+}
+pub trait StmtVisitor<R> {
+    fn visit_expression(&mut self, expression: &Expr) -> R;
+    fn visit_print(&mut self, expressions: &[Expr]) -> R;
+}
@@ -1,6 +1,7 @@
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenType {
     // Single-character tokens
     LeftParen,
@@ -14,10 +15,15 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Percent,
     Interro,
     Colon,
+    Ampersand,
+    Pipe,
+    Caret,
 
     // One or two character tokens
+    InterroInterro,
     Bang,
     BangEqual,
     Equal,
@@ -26,10 +32,13 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    GreaterGreater,
+    LessLess,
 
     // Literals
     Identifier,
     String,
+    Char,
     Number,
     True,
     False,
@@ -50,17 +59,147 @@ pub enum TokenType {
     Var,
     While,
 
+    // Only produced when the scanner is asked to keep comments
+    Comment,
+
     Eof,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+impl TokenType {
+    /// Binding level of the token as an infix operator, where a higher level
+    /// binds tighter. The Pratt parser is driven by this table, while the
+    /// recursive descent parser encodes it in its structure, so the two need
+    /// to be kept in sync.
+    ///
+    /// `-` is reported with its binary precedence; as a prefix operator it
+    /// binds tighter than any of these.
+    pub fn precedence(&self) -> Option<u8> {
+        match self {
+            TokenType::Comma => Some(1),
+            TokenType::Interro => Some(2),
+            TokenType::InterroInterro => Some(3),
+            TokenType::BangEqual | TokenType::EqualEqual => Some(4),
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => Some(5),
+            TokenType::Pipe => Some(6),
+            TokenType::Caret => Some(7),
+            TokenType::Ampersand => Some(8),
+            TokenType::GreaterGreater | TokenType::LessLess => Some(9),
+            TokenType::Minus | TokenType::Plus => Some(10),
+            TokenType::Slash | TokenType::Star | TokenType::Percent => Some(11),
+            _ => None,
+        }
+    }
+
+    /// Reserved words, including the literals `true`, `false` and `nil`
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenType::And
+                | TokenType::Class
+                | TokenType::Else
+                | TokenType::False
+                | TokenType::Fun
+                | TokenType::For
+                | TokenType::If
+                | TokenType::Nil
+                | TokenType::Or
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Super
+                | TokenType::This
+                | TokenType::True
+                | TokenType::Var
+                | TokenType::While
+        )
+    }
+
+    /// Tokens that stand for a value of their own
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            TokenType::String
+                | TokenType::Char
+                | TokenType::Number
+                | TokenType::True
+                | TokenType::False
+                | TokenType::Nil
+        )
+    }
+
+    /// Prefix, infix and assignment operators. The logical `and` and `or` are
+    /// keywords instead, and brackets, `.` and `;` are punctuation.
+    pub fn is_operator(&self) -> bool {
+        self.precedence().is_some()
+            || matches!(self, TokenType::Bang | TokenType::Colon | TokenType::Equal)
+    }
+
+    /// Associativity of the token as an infix operator.
+    pub fn associativity(&self) -> Option<Associativity> {
+        match self {
+            TokenType::Interro | TokenType::InterroInterro => Some(Associativity::Right),
+            _ => self.precedence().map(|_| Associativity::Left),
+        }
+    }
+}
+
+/// A literal value. The derived `PartialEq` compares numbers exactly, so the
+/// result of a calculation may not equal the literal it should come out as;
+/// `approx_eq` allows for rounding.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Literal {
     String(String),
+    /// A single character in single quotes, which evaluates to a string of
+    /// just that character
+    Char(char),
     Number(f64),
     Bool(bool),
     Nil(),
 }
 
+impl Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::String(s) => write!(f, "{}", s),
+            Literal::Char(c) => write!(f, "{}", c),
+            // Spelled like the reference implementation, which uses Java's
+            // names for these
+            Literal::Number(n) if n.is_nan() => write!(f, "NaN"),
+            Literal::Number(n) if n.is_infinite() && *n < 0.0 => write!(f, "-Infinity"),
+            Literal::Number(n) if n.is_infinite() => write!(f, "Infinity"),
+            // f64's Display already leaves off the fractional part for
+            // integral values and never resorts to an exponent, which is what
+            // Lox wants. Negative zero keeps its sign, as in the reference
+            // implementation.
+            Literal::Number(n) => write!(f, "{}", n),
+            Literal::Bool(value) => write!(f, "{}", value),
+            Literal::Nil() => write!(f, "nil"),
+        }
+    }
+}
+
+impl Literal {
+    /// Equality that lets numbers differ by a few units in the last place,
+    /// like `0.1 + 0.2` and `0.3`. Anything else has to be equal.
+    pub fn approx_eq(&self, other: &Literal) -> bool {
+        match (self, other) {
+            (Literal::Number(lhs), Literal::Number(rhs)) => {
+                lhs == rhs || (lhs - rhs).abs() <= 4.0 * f64::EPSILON * lhs.abs().max(rhs.abs())
+            }
+            _ => self == other,
+        }
+    }
+}
+
 impl From<&str> for Literal {
     fn from(string: &str) -> Self {
         Literal::String(string.into())
@@ -68,6 +207,7 @@ impl From<&str> for Literal {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub token_type: TokenType,
     // Fun Fact™: In a previous iteration, `lexeme` was a &str slice of the
@@ -77,24 +217,33 @@ pub struct Token {
     pub lexeme: String,
     pub literal: Option<Literal>,
     pub line: usize,
+    pub column: usize,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: &str, line: usize) -> Self {
+    pub fn new(token_type: TokenType, lexeme: &str, line: usize, column: usize) -> Self {
         Token {
             token_type,
             lexeme: lexeme.into(),
             literal: None,
             line,
+            column,
         }
     }
 
-    pub fn new_literal(token_type: TokenType, lexeme: &str, literal: Literal, line: usize) -> Self {
+    pub fn new_literal(
+        token_type: TokenType,
+        lexeme: &str,
+        literal: Literal,
+        line: usize,
+        column: usize,
+    ) -> Self {
         Token {
             token_type,
             lexeme: lexeme.into(),
             literal: Some(literal),
             line,
+            column,
         }
     }
 }
@@ -107,3 +256,159 @@ impl Display for Token {
         }
     }
 }
+
+impl Token {
+    /// Columns the token covers on the line it starts on. Tokens without a
+    /// lexeme, like Eof, still cover the column they're at.
+    pub fn columns(&self) -> Range<usize> {
+        let width = self
+            .lexeme
+            .lines()
+            .next()
+            .map_or(0, |line| line.chars().count());
+        self.column..self.column + width.max(1)
+    }
+
+    /// Display the token with its position, like `1:5 Plus "+"`, for dumping
+    /// token streams.
+    pub fn display_verbose(&self) -> VerboseToken<'_> {
+        VerboseToken(self)
+    }
+}
+
+/// Display wrapper returned by `Token::display_verbose`
+pub struct VerboseToken<'token>(&'token Token);
+
+impl Display for VerboseToken<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let token = self.0;
+        write!(
+            f,
+            "{}:{} {:?} {:?}",
+            token.line, token.column, token.token_type, token.lexeme
+        )?;
+        match &token.literal {
+            Some(literal) => write!(f, " {:?}", literal),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn precedence() {
+        assert!(TokenType::Star.precedence() > TokenType::Plus.precedence());
+        assert!(TokenType::EqualEqual.precedence() < TokenType::Less.precedence());
+        assert!(TokenType::Comma.precedence() < TokenType::Interro.precedence());
+        assert!(TokenType::Interro.precedence() < TokenType::InterroInterro.precedence());
+        assert!(TokenType::InterroInterro.precedence() < TokenType::EqualEqual.precedence());
+        assert!(TokenType::Less.precedence() < TokenType::Pipe.precedence());
+        assert!(TokenType::Pipe.precedence() < TokenType::Caret.precedence());
+        assert!(TokenType::Caret.precedence() < TokenType::Ampersand.precedence());
+        assert!(TokenType::Ampersand.precedence() < TokenType::LessLess.precedence());
+        assert!(TokenType::GreaterGreater.precedence() < TokenType::Plus.precedence());
+        assert_eq!(TokenType::LeftParen.precedence(), None);
+    }
+
+    #[test]
+    fn classification() {
+        assert!(TokenType::While.is_keyword());
+        assert!(TokenType::Plus.is_operator());
+        assert!(TokenType::Number.is_literal());
+
+        assert!(TokenType::Nil.is_keyword() && TokenType::Nil.is_literal());
+        assert!(TokenType::Bang.is_operator() && TokenType::Comma.is_operator());
+        for token_type in [
+            TokenType::Identifier,
+            TokenType::LeftParen,
+            TokenType::Dot,
+            TokenType::Semicolon,
+            TokenType::Eof,
+        ] {
+            assert!(!token_type.is_keyword(), "{:?}", token_type);
+            assert!(!token_type.is_literal(), "{:?}", token_type);
+            assert!(!token_type.is_operator(), "{:?}", token_type);
+        }
+
+        // Everything the scanner treats as a keyword
+        let keywords =
+            "and class else false fun for if nil or print return super this true var while";
+        let tokens = crate::scanner::Scanner::new(keywords)
+            .scan_tokens()
+            .unwrap();
+        for token in &tokens[..tokens.len() - 1] {
+            assert!(token.token_type.is_keyword(), "{}", token.lexeme);
+        }
+    }
+
+    #[test]
+    fn display_literal() {
+        assert_eq!(Literal::Number(4.0).to_string(), "4");
+        assert_eq!(Literal::Number(4.5).to_string(), "4.5");
+        assert_eq!(Literal::Number(1000000.0).to_string(), "1000000");
+        assert_eq!(Literal::Number(-0.0).to_string(), "-0");
+        assert_eq!(
+            Literal::Number(0.1 + 0.2).to_string(),
+            "0.30000000000000004"
+        );
+        assert_eq!(Literal::Number(1e21).to_string(), "1000000000000000000000");
+        assert_eq!(Literal::Number(2.5e-7).to_string(), "0.00000025");
+        assert_eq!(Literal::from("some text").to_string(), "some text");
+        assert_eq!(Literal::Char('x').to_string(), "x");
+        assert_eq!(Literal::Bool(false).to_string(), "false");
+        assert_eq!(Literal::Nil().to_string(), "nil");
+        assert_eq!(Literal::Number(f64::NAN).to_string(), "NaN");
+        assert_eq!(Literal::Number(f64::INFINITY).to_string(), "Infinity");
+        assert_eq!(Literal::Number(f64::NEG_INFINITY).to_string(), "-Infinity");
+    }
+
+    #[test]
+    fn display_token() {
+        let token = Token::new(TokenType::Plus, "+", 1, 5);
+        assert_eq!(token.to_string(), "Plus +");
+        assert_eq!(token.display_verbose().to_string(), "1:5 Plus \"+\"");
+
+        let token = Token::new_literal(TokenType::Number, "4.0", Literal::Number(4.0), 2, 3);
+        assert_eq!(
+            token.display_verbose().to_string(),
+            "2:3 Number \"4.0\" Number(4.0)"
+        );
+    }
+
+    #[test]
+    fn approx_eq() {
+        let sum = Literal::Number(0.1 + 0.2);
+        assert_ne!(sum, Literal::Number(0.3));
+        assert!(sum.approx_eq(&Literal::Number(0.3)));
+        assert!(!sum.approx_eq(&Literal::Number(0.30001)));
+        assert!(Literal::Number(0.0).approx_eq(&Literal::Number(-0.0)));
+        assert!(!Literal::Number(f64::NAN).approx_eq(&Literal::Number(f64::NAN)));
+        assert!(Literal::from("a").approx_eq(&Literal::from("a")));
+        assert!(!Literal::Number(1.0).approx_eq(&Literal::from("1")));
+    }
+
+    #[test]
+    fn columns() {
+        assert_eq!(Token::new(TokenType::BangEqual, "!=", 1, 5).columns(), 5..7);
+        assert_eq!(Token::new(TokenType::Eof, "", 3, 2).columns(), 2..3);
+        let string = Token::new_literal(TokenType::String, "\"ab\ncd\"", "ab\ncd".into(), 1, 4);
+        assert_eq!(string.columns(), 4..7);
+    }
+
+    #[test]
+    fn associativity() {
+        assert_eq!(TokenType::Minus.associativity(), Some(Associativity::Left));
+        assert_eq!(
+            TokenType::Interro.associativity(),
+            Some(Associativity::Right)
+        );
+        assert_eq!(
+            TokenType::InterroInterro.associativity(),
+            Some(Associativity::Right)
+        );
+        assert_eq!(TokenType::Bang.associativity(), None);
+    }
+}
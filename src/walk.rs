@@ -0,0 +1,131 @@
+use std::collections::BTreeSet;
+
+use crate::{expr::Expr, stmt::Stmt};
+
+/// Hand-written additions to the generated Expr, for queries over a whole tree
+/// that don't warrant a visitor of their own.
+impl Expr {
+    /// The expressions directly below this one, from left to right
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Binary { lhs, rhs, .. } | Expr::Logical { lhs, rhs, .. } => vec![lhs, rhs],
+            Expr::Ternary { lhs, mhs, rhs, .. } => vec![lhs, mhs, rhs],
+            Expr::Call {
+                callee, arguments, ..
+            } => std::iter::once(callee.as_ref())
+                .chain(arguments.iter())
+                .collect(),
+            Expr::Get { object, .. } => vec![object],
+            Expr::Grouping { expression } => vec![expression],
+            Expr::Literal { .. } | Expr::Variable { .. } => vec![],
+            Expr::Unary { operand, .. } => vec![operand],
+            Expr::Block { statements, value } => statements
+                .iter()
+                .flat_map(Stmt::expressions)
+                .chain(std::iter::once(value.as_ref()))
+                .collect(),
+        }
+    }
+
+    /// Every expression in the tree, starting with this one, in pre-order:
+    /// each expression comes before its children, which are visited left to
+    /// right.
+    pub fn descendants(&self) -> Descendants<'_> {
+        Descendants { stack: vec![self] }
+    }
+
+    /// The names of all variables the expression refers to but doesn't
+    /// declare. Property names aren't variables. Since nothing can declare a
+    /// variable yet, that's every variable in the tree.
+    pub fn free_variables(&self) -> BTreeSet<&str> {
+        self.descendants()
+            .filter_map(|expr| match expr {
+                Expr::Variable { name } => Some(name.lexeme.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Stmt {
+    /// The expressions directly in this statement, from left to right
+    pub fn expressions(&self) -> Vec<&Expr> {
+        match self {
+            Stmt::Expression { expression } => vec![expression],
+            Stmt::Print { expressions } => expressions.iter().collect(),
+        }
+    }
+}
+
+/// Iterator returned by `Expr::descendants`
+pub struct Descendants<'expr> {
+    /// Expressions yet to be visited, the next one last
+    stack: Vec<&'expr Expr>,
+}
+
+impl<'expr> Iterator for Descendants<'expr> {
+    type Item = &'expr Expr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let expr = self.stack.pop()?;
+        self.stack.extend(expr.children().into_iter().rev());
+        Some(expr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{metrics::NodeCounter, parser::Parser, scanner::Scanner, token::Literal};
+
+    fn parse(source: &str) -> Expr {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        Parser::new(&tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn descendants() {
+        let expr = parse("f(1, -x).y ? (\"a\" + 2) : b ?? 3");
+        assert_eq!(expr.descendants().count(), 14);
+        assert_eq!(
+            expr.descendants().count(),
+            NodeCounter {}.count(&expr).total()
+        );
+
+        let literals: Vec<&Literal> = expr
+            .descendants()
+            .filter_map(|expr| match expr {
+                Expr::Literal { value, .. } => Some(value.as_ref()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            literals,
+            [
+                &Literal::Number(1.0),
+                &Literal::from("a"),
+                &Literal::Number(2.0),
+                &Literal::Number(3.0)
+            ]
+        );
+
+        assert!(matches!(
+            expr.descendants().next(),
+            Some(Expr::Ternary { .. })
+        ));
+        assert_eq!(parse("1").descendants().count(), 1);
+    }
+
+    #[test]
+    fn free_variables() {
+        assert_eq!(
+            parse("a + b * a").free_variables(),
+            BTreeSet::from(["a", "b"])
+        );
+        assert_eq!(
+            parse("f(x).y ?? (z, len)").free_variables(),
+            BTreeSet::from(["f", "len", "x", "z"])
+        );
+        assert!(parse("1 + 2").free_variables().is_empty());
+    }
+}
@@ -0,0 +1,83 @@
+//! Tests that run the jlox binary itself, for behaviour that only exists on
+//! the command line.
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// Write a script to a file of its own and run jlox on it with the given
+/// arguments
+fn jlox(name: &str, source: &str, args: &[&str]) -> Output {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, source).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_jlox"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn check_clean_script() {
+    let output = jlox("check_clean.lox", "print 1 / 0;\n", &["--check"]);
+    // Checking doesn't run anything, so neither output nor runtime errors
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn check_broken_script() {
+    let output = jlox("check_broken.lox", "print 1;\nprint (2;\n", &["--check"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("E1001"));
+
+    let output = jlox("check_unscannable.lox", "print 1 @ 2;\n", &["--check"]);
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn warnings_as_errors() {
+    let source = "print 1 < 2 < 3;\n";
+    let output = jlox("warning.lox", source, &[]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("W0002"));
+
+    let source = "print true ? 1 : \"one\";\n";
+    let output = jlox("warning.lox", source, &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"1\n");
+    assert!(String::from_utf8(output.stderr).unwrap().contains("W0001"));
+
+    let output = jlox("warning.lox", source, &["--werror"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("W0001"));
+
+    let output = jlox("warning.lox", source, &["--werror", "--check"]);
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let source = format!("print {}1{};\n", "(".repeat(50000), ")".repeat(50000));
+    for args in [&[][..], &["--check"]] {
+        let output = jlox("deep.lox", &source, args);
+        assert_eq!(output.status.code(), Some(65), "{:?}", args);
+        assert!(String::from_utf8(output.stderr).unwrap().contains("E1007"));
+    }
+}
+
+#[test]
+fn deep_evaluation_is_an_error() {
+    // Operator chains nest without any parentheses
+    let source = format!("print {}1;\n", "1 + ".repeat(1000));
+    let output = jlox("deep_chain.lox", &source, &[]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("levels deep to evaluate"));
+}
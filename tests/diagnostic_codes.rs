@@ -0,0 +1,61 @@
+use jlox::{diagnostic::Diagnostic, parser::Parser, scanner::Scanner};
+
+fn scanner_code(source: &str) -> &'static str {
+    let errors = Scanner::new(source).scan_tokens().unwrap_err();
+    errors[0].code()
+}
+
+fn parser_code(source: &str) -> &'static str {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    Parser::new(&tokens).parse().unwrap_err().code()
+}
+
+#[test]
+fn scanner_error_codes() {
+    let cases = [
+        ("1 @ 2", "E0001"),
+        ("\"unterminated", "E0101"),
+        ("/* unterminated", "E0102"),
+        ("#line x", "E0005"),
+        ("\"\\xZZ\"", "E0104"),
+    ];
+
+    for (source, code) in cases {
+        assert_eq!(scanner_code(source), code, "source: {}", source);
+    }
+}
+
+#[test]
+fn parser_error_codes() {
+    let deep = "(".repeat(1000);
+    let cases = [
+        ("(1 + 2", "E1001"),
+        ("1 +", "E1002"),
+        ("true ? 1", "E1003"),
+        ("* 2", "E1004"),
+        ("a.1", "E1005"),
+        (&deep, "E1007"),
+        ("1 2", "E1009"),
+    ];
+
+    for (source, code) in cases {
+        assert_eq!(parser_code(source), code, "source: {}", source);
+    }
+}
+
+#[test]
+fn code_in_display() {
+    let tokens = Scanner::new("(1 + 2").scan_tokens().unwrap();
+    let error = Parser::new(&tokens).parse().unwrap_err();
+    assert!(error.to_string().starts_with("E1001: "));
+
+    let errors = Scanner::new("@").scan_tokens().unwrap_err();
+    assert_eq!(errors[0].to_string(), "E0001: 1:1: Unexpected character @");
+}
+
+#[test]
+fn scanner_error_column() {
+    let errors = Scanner::new("1;\n   $").scan_tokens().unwrap_err();
+    assert_eq!(errors[0].column(), 4);
+    assert_eq!(errors[0].to_string(), "E0001: 2:4: Unexpected character $");
+}
@@ -0,0 +1,372 @@
+use jlox::{
+    interpreter::{Interpreter, RuntimeError, Value},
+    parser::Parser,
+    scanner::Scanner,
+    Error,
+};
+
+fn evaluate(source: &str) -> Result<Value, RuntimeError> {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let expr = Parser::new(&tokens).parse().unwrap();
+    Interpreter::new().evaluate(&expr)
+}
+
+#[test]
+fn arithmetic() {
+    assert_eq!(evaluate("(4 + 2) / 3 - -1").unwrap(), Value::Number(3.0));
+    assert_eq!(
+        evaluate("\"con\" + \"cat\"").unwrap(),
+        Value::String("concat".into())
+    );
+    assert_eq!(evaluate("!nil == (1 < 2)").unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn modulo() {
+    assert_eq!(evaluate("7 % 3 == 1").unwrap(), Value::Bool(true));
+    assert_eq!(evaluate("-7.5 % 2").unwrap(), Value::Number(-1.5));
+    assert_eq!(
+        evaluate("7 % \"3\"").unwrap_err().message,
+        "Operands must be numbers."
+    );
+}
+
+#[test]
+fn division_by_zero() {
+    for source in ["1 / 0", "0 / 0", "1 / -0", "1 % 0"] {
+        let error = evaluate(source).unwrap_err();
+        assert_eq!(error.message, "Division by zero.", "source: {}", source);
+    }
+    let error = evaluate("1 / 0").unwrap_err();
+    assert_eq!(error.token.lexeme, "/");
+    assert_eq!(evaluate("0 / 2").unwrap(), Value::Number(0.0));
+}
+
+#[test]
+fn type_errors() {
+    let error = evaluate("1 + \"one\"").unwrap_err();
+    assert_eq!(
+        error.message,
+        "Operands must be two numbers or two strings."
+    );
+    assert_eq!(
+        evaluate("-true").unwrap_err().message,
+        "Operand must be a number."
+    );
+}
+
+#[test]
+fn comma_yields_right_operand() {
+    assert_eq!(evaluate("1, 2").unwrap(), Value::Number(2.0));
+    assert_eq!(evaluate("1 + 2, 3 * 4").unwrap(), Value::Number(12.0));
+    assert_eq!(evaluate("(1, 2) + 1").unwrap(), Value::Number(3.0));
+}
+
+#[test]
+fn comma_evaluates_left_operand() {
+    // There's no assignment yet, so the only observable effect of evaluating
+    // the discarded left operand is the error it raises.
+    let error = evaluate("-\"a\", 2").unwrap_err();
+    assert_eq!(error.message, "Operand must be a number.");
+    assert_eq!(error.token.lexeme, "-");
+}
+
+#[test]
+fn ternary_selects_branch() {
+    assert_eq!(evaluate("true ? 1 : 2").unwrap(), Value::Number(1.0));
+    assert_eq!(evaluate("nil ? 1 : 2").unwrap(), Value::Number(2.0));
+    assert_eq!(
+        evaluate("0 ? \"zero\" : 2").unwrap(),
+        Value::String("zero".into())
+    );
+    assert_eq!(
+        evaluate("false ? 1 : nil ? 2 : 3").unwrap(),
+        Value::Number(3.0)
+    );
+}
+
+#[test]
+fn ternary_short_circuits() {
+    // Evaluating either of the erroring branches would fail the whole
+    // expression, so success means they were skipped.
+    assert_eq!(evaluate("true ? 1 : -\"a\"").unwrap(), Value::Number(1.0));
+    assert_eq!(evaluate("false ? -\"a\" : 2").unwrap(), Value::Number(2.0));
+    assert!(evaluate("-\"a\" ? 1 : 2").is_err());
+}
+
+#[test]
+fn no_variables_or_callables_yet() {
+    assert_eq!(
+        evaluate("a.b()").unwrap_err().message,
+        "Undefined variable 'a'."
+    );
+    assert_eq!(
+        evaluate("\"text\".length").unwrap_err().message,
+        "Only instances have properties."
+    );
+    assert_eq!(
+        evaluate("1(2)").unwrap_err().message,
+        "Can only call functions and classes."
+    );
+}
+
+#[test]
+fn string_natives() {
+    assert_eq!(evaluate("len(\"abc\")").unwrap(), Value::Number(3.0));
+    assert_eq!(evaluate("len(\"\")").unwrap(), Value::Number(0.0));
+    assert_eq!(
+        evaluate("substring(\"hello\", 1, 3)").unwrap(),
+        Value::String("el".into())
+    );
+    assert_eq!(
+        evaluate("substring(\"hello\", 5, 5)").unwrap(),
+        Value::String("".into())
+    );
+}
+
+#[test]
+fn string_native_errors() {
+    let error = evaluate("substring(\"abc\", 1, 4)").unwrap_err();
+    assert_eq!(
+        error.message,
+        "Substring range 1..4 is out of bounds for a string of length 3."
+    );
+    assert_eq!(error.token.lexeme, ")");
+
+    assert_eq!(
+        evaluate("substring(\"abc\", 2, 1)").unwrap_err().message,
+        "Substring range 2..1 is out of bounds for a string of length 3."
+    );
+    assert_eq!(
+        evaluate("substring(\"abc\", 0.5, 1)").unwrap_err().message,
+        "Index must be a non-negative whole number."
+    );
+    assert_eq!(
+        evaluate("len(3)").unwrap_err().message,
+        "Argument must be a string."
+    );
+    assert_eq!(
+        evaluate("len(\"a\", \"b\")").unwrap_err().message,
+        "Expected 1 arguments but got 2."
+    );
+}
+
+#[test]
+fn conversion_natives() {
+    assert_eq!(evaluate("number(\"3.5\")").unwrap(), Value::Number(3.5));
+    assert_eq!(
+        evaluate("number(\" -2 \") + 1").unwrap(),
+        Value::Number(-1.0)
+    );
+    assert_eq!(
+        evaluate("number(\"x\")").unwrap_err().message,
+        "Can't convert 'x' to a number."
+    );
+    assert_eq!(
+        evaluate("number(\"inf\")").unwrap_err().message,
+        "Can't convert 'inf' to a number."
+    );
+
+    assert_eq!(evaluate("string(42)").unwrap(), Value::String("42".into()));
+    assert_eq!(
+        evaluate("string(nil) + string(1 < 2)").unwrap(),
+        Value::String("niltrue".into())
+    );
+    assert_eq!(
+        evaluate("string(len)").unwrap(),
+        Value::String("<native fn>".into())
+    );
+}
+
+#[test]
+fn assert_native() {
+    assert_eq!(
+        evaluate("assert(1 + 1 == 2, \"math\")").unwrap(),
+        Value::Nil()
+    );
+    assert_eq!(
+        evaluate("assert(0, \"zero is truthy\")").unwrap(),
+        Value::Nil()
+    );
+
+    let error = evaluate("assert(1 > 2, \"1 is not greater than 2\")").unwrap_err();
+    assert_eq!(error.message, "1 is not greater than 2");
+    assert_eq!(error.token.line, 1);
+    assert_eq!(evaluate("assert(nil, 42)").unwrap_err().message, "42");
+}
+
+#[test]
+fn string_comparison() {
+    assert_eq!(
+        evaluate("\"apple\" < \"banana\"").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(evaluate("\"b\" >= \"ab\"").unwrap(), Value::Bool(true));
+    assert_eq!(evaluate("\"a\" <= \"a\"").unwrap(), Value::Bool(true));
+    assert_eq!(evaluate("\"a\" > \"a\"").unwrap(), Value::Bool(false));
+    assert_eq!(
+        evaluate("\"a\" < 1").unwrap_err().message,
+        "Operands must be two numbers or two strings."
+    );
+    assert_eq!(
+        evaluate("nil >= nil").unwrap_err().message,
+        "Operands must be two numbers or two strings."
+    );
+}
+
+#[test]
+fn type_native() {
+    let cases = [
+        ("type(1)", "number"),
+        ("type(\"x\")", "string"),
+        ("type(nil)", "nil"),
+        ("type(1 < 2)", "bool"),
+        ("type(len)", "function"),
+        ("type(type(1))", "string"),
+    ];
+
+    for (source, type_name) in cases {
+        assert_eq!(
+            evaluate(source).unwrap(),
+            Value::String(type_name.into()),
+            "source: {}",
+            source
+        );
+    }
+}
+
+#[test]
+fn coalesce() {
+    assert_eq!(evaluate("nil ?? 5").unwrap(), Value::Number(5.0));
+    assert_eq!(evaluate("false ?? 5").unwrap(), Value::Bool(false));
+    assert_eq!(evaluate("nil ?? nil ?? 5").unwrap(), Value::Number(5.0));
+    // The right operand would fail if it were evaluated
+    assert_eq!(evaluate("3 ?? -\"a\"").unwrap(), Value::Number(3.0));
+    assert!(evaluate("nil ?? -\"a\"").is_err());
+}
+
+#[test]
+fn bitwise() {
+    let cases = [
+        ("1 << 4", 16.0),
+        ("256 >> 2", 64.0),
+        ("12 & 10", 8.0),
+        ("12 | 10", 14.0),
+        ("12 ^ 10", 6.0),
+        ("7.9 & 3.2", 3.0),
+        ("-1 & 255", 255.0),
+    ];
+
+    for (source, expected) in cases {
+        assert_eq!(
+            evaluate(source).unwrap(),
+            Value::Number(expected),
+            "source: {}",
+            source
+        );
+    }
+
+    // Unlike C, the bitwise operators bind tighter than comparisons
+    assert_eq!(evaluate("1 | 2 == 3").unwrap(), Value::Bool(true));
+    assert_eq!(
+        evaluate("1 & \"1\"").unwrap_err().message,
+        "Operands must be numbers."
+    );
+    assert_eq!(
+        evaluate("1 << 64").unwrap_err().message,
+        "Shift amount must be between 0 and 63."
+    );
+    assert!(evaluate("1 >> -1").is_err());
+}
+
+#[test]
+fn nan_and_infinity() {
+    // There are no literals for these, but a number too large for a double
+    // comes out as infinity, and NaN follows from that
+    let huge = format!("1{}", "0".repeat(200));
+    let infinity = format!("{} * {}", huge, huge);
+    let nan = format!("({}) - ({})", infinity, infinity);
+
+    assert_eq!(evaluate(&infinity).unwrap().to_string(), "Infinity");
+    assert_eq!(
+        evaluate(&format!("-({})", infinity)).unwrap().to_string(),
+        "-Infinity"
+    );
+    assert_eq!(evaluate(&nan).unwrap().to_string(), "NaN");
+
+    // NaN compares as in IEEE 754: unequal to everything, itself included
+    for comparison in ["==", "<", "<=", ">", ">="] {
+        let source = format!("({}) {} ({})", nan, comparison, nan);
+        assert_eq!(
+            evaluate(&source).unwrap(),
+            Value::Bool(false),
+            "{}",
+            comparison
+        );
+    }
+    assert_eq!(
+        evaluate(&format!("({}) != ({})", nan, nan)).unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        evaluate(&format!("type({})", nan)).unwrap(),
+        Value::String("number".into())
+    );
+}
+
+#[test]
+fn eval_str() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.eval_str("len(\"abc\") * 2").unwrap(),
+        Value::Number(6.0)
+    );
+    // The same interpreter, globals and all, is used for every call
+    interpreter.define_global("width", Value::Number(4.0));
+    assert_eq!(
+        interpreter.eval_str("width * 2").unwrap(),
+        Value::Number(8.0)
+    );
+    assert!(matches!(
+        Interpreter::new().eval_str("width * 2"),
+        Err(Error::Runtime(_))
+    ));
+
+    assert!(matches!(
+        interpreter.eval_str("1 @ 2"),
+        Err(Error::Scanner(_))
+    ));
+    assert!(matches!(interpreter.eval_str("1 +"), Err(Error::Parser(_))));
+    assert!(matches!(
+        interpreter.eval_str("1 + nil"),
+        Err(Error::Runtime(_))
+    ));
+}
+
+#[test]
+fn define_global() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_global("version", Value::String("1.2".into()));
+    assert_eq!(
+        interpreter.eval_str("\"v\" + version").unwrap(),
+        Value::String("v1.2".into())
+    );
+
+    interpreter.define_global("len", Value::Number(3.0));
+    assert_eq!(interpreter.eval_str("len").unwrap(), Value::Number(3.0));
+}
+
+#[test]
+fn block_expressions() {
+    let tokens = Scanner::new("{ len(\"abc\"); 1 + 2 } * { 4 }")
+        .scan_tokens()
+        .unwrap();
+    let expr = Parser::new(&tokens)
+        .block_expressions(true)
+        .parse()
+        .unwrap();
+    assert_eq!(
+        Interpreter::new().evaluate(&expr).unwrap(),
+        Value::Number(12.0)
+    );
+}
@@ -0,0 +1,75 @@
+//! Golden-file tests for the AST printer: every `tests/cases/<name>.lox` is
+//! parsed and printed, and the result compared to `tests/cases/<name>.sexpr`.
+
+use std::{fs, path::Path};
+
+use jlox::{ast_printer::AstPrinter, parser::Parser, scanner::Scanner};
+
+fn print(source: &str) -> Result<String, String> {
+    let tokens = Scanner::new(source)
+        .scan_tokens()
+        .map_err(|errors| format!("{:?}", errors))?;
+    let expr = Parser::new(&tokens)
+        .parse()
+        .map_err(|error| error.to_string())?;
+    Ok(AstPrinter::default().print(&expr))
+}
+
+/// Line-by-line comparison of expected and actual output, marking lines that
+/// only appear in one of them with - or +.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut result = String::new();
+
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => result += &format!("  {}\n", e),
+            (e, a) => {
+                if let Some(e) = e {
+                    result += &format!("- {}\n", e);
+                }
+                if let Some(a) = a {
+                    result += &format!("+ {}\n", a);
+                }
+            }
+        }
+    }
+    result
+}
+
+#[test]
+fn golden_files() {
+    let cases = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cases");
+    let mut inputs: Vec<_> = fs::read_dir(&cases)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "lox"))
+        .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty(), "No cases in {}", cases.display());
+
+    let mut failures = Vec::new();
+    for input in &inputs {
+        let source = fs::read_to_string(input).unwrap();
+        let expected = fs::read_to_string(input.with_extension("sexpr"))
+            .unwrap_or_else(|_| panic!("Missing expected output for {}", input.display()));
+
+        let actual = print(&source).unwrap_or_else(|error| format!("error: {}", error));
+        if actual.trim_end() != expected.trim_end() {
+            failures.push(format!(
+                "{}:\n{}",
+                input.display(),
+                diff(expected.trim_end(), &actual)
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} cases failed (- expected, + actual):\n{}",
+        failures.len(),
+        inputs.len(),
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,57 @@
+//! The single-line and multiline forms of the AST printer, side by side, so
+//! that changes to either show up here.
+
+use jlox::{ast_printer::AstPrinter, parser::Parser, scanner::Scanner};
+
+/// Sources with their single-line and multiline prints
+const CASES: &[(&str, &str, &str)] = &[
+    ("1", "1", "1"),
+    ("-1", "(- 1)", "(- 1)"),
+    ("!-x", "(! (- x))", "(!\n  (- x))"),
+    ("1 + 2", "(+ 1 2)", "(+ 1 2)"),
+    ("(1)", "(group 1)", "(group 1)"),
+    // Leaves can print with parentheses of their own
+    ("\"(x\" + 1", "(+ (x 1)", "(+ (x 1)"),
+    ("((a))", "(group (group a))", "(group\n  (group a))"),
+    ("1, 2, 3", "(, (, 1 2) 3)", "(,\n  (, 1 2)\n  3)"),
+    ("a ? b : c", "(?: a b c)", "(?: a b c)"),
+    (
+        "a == 1 ? -b : (c, d)",
+        "(?: (== a 1) (- b) (group (, c d)))",
+        "(?:\n  (== a 1)\n  (- b)\n  (group\n    (, c d)))",
+    ),
+    (
+        "f(1, -2).g",
+        "(. (call f 1 (- 2)) g)",
+        "(.\n  (call\n    f\n    1\n    (- 2))\n  g)",
+    ),
+];
+
+fn print(source: &str, multiline: bool) -> String {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let expr = Parser::new(&tokens).parse().unwrap();
+    AstPrinter::default().multiline(multiline).print(&expr)
+}
+
+#[test]
+fn single_line() {
+    for (source, single_line, _) in CASES {
+        assert_eq!(print(source, false), *single_line, "source: {}", source);
+    }
+}
+
+#[test]
+fn multiline() {
+    for (source, _, multiline) in CASES {
+        assert_eq!(print(source, true), *multiline, "source: {}", source);
+    }
+}
+
+#[test]
+fn forms_agree() {
+    for (source, _, _) in CASES {
+        let multiline = print(source, true);
+        let collapsed = multiline.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert_eq!(collapsed, print(source, false), "source: {}", source);
+    }
+}
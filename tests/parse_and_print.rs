@@ -1,14 +1,18 @@
-use jlox::{self, expr::Expr, parser::ParserError};
+use jlox::{
+    self,
+    expr::Expr,
+    parser::{ParserError, ParserErrorKind},
+};
 
 fn parse(source: &str) -> Result<Expr, ParserError> {
     let mut scanner = jlox::scanner::Scanner::new(source);
     let tokens = scanner.scan_tokens().unwrap();
-    jlox::parser::Parser { tokens: &tokens }.parse()
+    jlox::parser::Parser::new(&tokens).parse()
 }
 
 fn source_and_print(source: &str, print: &str) {
     let ast = parse(source).unwrap();
-    let mut printer = jlox::ast_printer::AstPrinter {};
+    let mut printer = jlox::ast_printer::AstPrinter::default();
     assert_eq!(printer.print(&ast), print);
 }
 
@@ -17,6 +21,12 @@ fn simple_expression() {
     source_and_print("4 + true", "(+ 4 true)");
 }
 
+#[test]
+fn modulo() {
+    source_and_print("7 % 3 == 1", "(== (% 7 3) 1)");
+    source_and_print("1 + 7 % 3 * 2", "(+ 1 (* (% 7 3) 2))");
+}
+
 #[test]
 fn comma_operator() {
     source_and_print("5 == 1, 0", "(, (== 5 1) 0)");
@@ -30,6 +40,15 @@ fn compound_expression() {
     );
 }
 
+#[test]
+fn calls_and_properties() {
+    source_and_print("a.b.c()", "(call (. (. a b) c))");
+    source_and_print("f(1, 2 + x)(3)", "(call (call f 1 (+ 2 x)) 3)");
+    source_and_print("-a.b * c()", "(* (- (. a b)) (call c))");
+    // The comma operator is still available inside an argument when grouped
+    source_and_print("f((1, 2))", "(call f (group (, 1 2)))");
+}
+
 #[test]
 fn chained_ternary() {
     source_and_print("1 ? 2 : 3 ? 4 : 5", "(?: 1 2 (?: 3 4 5))");
@@ -38,6 +57,33 @@ fn chained_ternary() {
     // The inner operand can be any expression, including the otherwise
     // lower-precedence comma operator.
     source_and_print("1 ? 2, 3 : 4", "(?: 1 (, 2 3) 4)");
+    // A ternary as the condition has to be grouped
+    source_and_print("(1 ? 2 : 3) ? 4 : 5", "(?: (group (?: 1 2 3)) 4 5)");
+}
+
+#[test]
+fn long_ternary_chain() {
+    let source = (1..=50)
+        .map(|n| format!("x == {} ? {} : ", n, n * 10))
+        .collect::<String>()
+        + "0";
+    let print = (1..=50)
+        .map(|n| format!("(?: (== x {}) {} ", n, n * 10))
+        .collect::<String>()
+        + "0"
+        + &")".repeat(50);
+    source_and_print(&source, &print);
+}
+
+#[test]
+fn ternary_missing_colon() {
+    let error = parse("a\n  ? b ? c : d\n  ; e").unwrap_err();
+    assert_eq!(error.kind, ParserErrorKind::MissingColon);
+    assert_eq!(error.token.line, 3);
+    assert_eq!(
+        error.message,
+        "Unexpected token ';'. Expected ':' for the '?' at line 2, column 3"
+    );
 }
 
 #[test]
@@ -45,7 +91,7 @@ fn endless_group() {
     let error = parse("6 + (!true * ").unwrap_err();
     assert_eq!(
         error.message,
-        "Unexpected end of file. Expected one of Number, String, True, False, Nil, or (Expr)"
+        "Unexpected end of file. Expected one of Number, String, Char, True, False, Nil, Identifier, or (Expr)"
     );
 }
 
@@ -54,7 +100,7 @@ fn incomplete_binary() {
     let error = parse("(6 + )").unwrap_err();
     assert_eq!(
         error.message,
-        "Unexpected token ')'. Expected one of Number, String, True, False, Nil, or (Expr)"
+        "Unexpected token ')'. Expected one of Number, String, Char, True, False, Nil, Identifier, or (Expr)"
     );
 }
 
@@ -63,3 +109,12 @@ fn unexpected_identifier() {
     let error = parse("(5 + 4 q)").unwrap_err();
     assert_eq!(error.message, "Unexpected token 'q'. Unterminated (Expr)");
 }
+
+#[test]
+fn round_trip_numbers() {
+    let ast = parse("4.0 + 007 * 2.50 - 3").unwrap();
+    let mut printer = jlox::ast_printer::AstPrinter::default().round_trip(true);
+    assert_eq!(printer.print(&ast), "(- (+ 4.0 (* 007 2.50)) 3)");
+
+    source_and_print("4.0 + 007 * 2.50 - 3", "(- (+ 4 (* 7 2.5)) 3)");
+}
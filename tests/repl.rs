@@ -0,0 +1,126 @@
+use jlox::{
+    diagnostic::Diagnostic,
+    interpreter::Value,
+    repl::{MetaCommandError, Mode, Repl},
+    Error,
+};
+
+#[test]
+fn entries_continue_line_numbers() {
+    let mut repl = Repl::new();
+    assert_eq!(repl.run("print 1;\n").unwrap(), None);
+
+    match repl.run("1 + )\n") {
+        Err(Error::Parser(error)) => assert_eq!(error.line(), 2),
+        other => panic!("Expected a parser error, got {:?}", other),
+    }
+
+    match repl.run("\"a\" +\n1;\n") {
+        Err(Error::Runtime(error)) => assert_eq!(error.token.line, 3),
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+
+    match repl.run("@\n") {
+        Err(Error::Scanner(errors)) => assert_eq!(errors[0].line(), 5),
+        other => panic!("Expected scanner errors, got {:?}", other),
+    }
+}
+
+#[test]
+fn expression_entries() {
+    let mut repl = Repl::new();
+    assert_eq!(repl.run("1 + 2\n").unwrap(), Some(Value::Number(3.0)));
+    assert_eq!(repl.run("1 + 2;\n").unwrap(), None);
+    assert!(matches!(repl.run("1 +\n"), Err(Error::Parser(_))));
+}
+
+#[test]
+fn comma_entry_prints_last_operand() {
+    let mut repl = Repl::new();
+    assert_eq!(repl.run("1, 2, 3\n").unwrap(), Some(Value::Number(3.0)));
+
+    // Earlier operands are still evaluated, so their errors surface
+    match repl.run("-\"a\", 3\n") {
+        Err(Error::Runtime(error)) => assert_eq!(error.message, "Operand must be a number."),
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn show_in_each_mode() {
+    let mut repl = Repl::new();
+    assert_eq!(repl.mode(), Mode::Value);
+    assert_eq!(repl.show("1 + 2\n").unwrap(), Some("3".into()));
+
+    repl.set_mode(Mode::Typed);
+    assert_eq!(repl.show("1 + 2\n").unwrap(), Some("3 : number".into()));
+
+    repl.set_mode(Mode::Ast);
+    assert_eq!(repl.show("1 + 2\n").unwrap(), Some("(+ 1 2)".into()));
+
+    // Statements have no value to show in any mode
+    assert_eq!(repl.show("1 + 2;\n").unwrap(), None);
+}
+
+#[test]
+fn mode_meta_command() {
+    let mut repl = Repl::new();
+    assert_eq!(repl.meta_command(":mode\n"), Some(Ok("mode: value".into())));
+    assert_eq!(
+        repl.meta_command(":mode typed\n"),
+        Some(Ok("mode: typed".into()))
+    );
+    assert_eq!(repl.mode(), Mode::Typed);
+    assert_eq!(repl.show("\"a\"\n").unwrap(), Some("a : string".into()));
+
+    assert_eq!(
+        repl.meta_command(":mode fancy\n"),
+        Some(Err(MetaCommandError::UnknownMode("fancy".into())))
+    );
+    assert_eq!(
+        repl.meta_command(":quit\n"),
+        Some(Err(MetaCommandError::UnknownCommand("quit".into())))
+    );
+    assert_eq!(repl.mode(), Mode::Typed);
+    assert_eq!(repl.meta_command("1 + 2\n"), None);
+
+    // Meta-commands still take up lines of the session
+    match repl.run("1 +\n") {
+        Err(Error::Parser(error)) => assert_eq!(error.line(), 6),
+        other => panic!("Expected a parser error, got {:?}", other),
+    }
+}
+
+#[test]
+fn print_without_semicolon() {
+    let mut repl = Repl::new();
+    assert_eq!(repl.run("print 1 + 2\n").unwrap(), None);
+    assert_eq!(repl.run("print \"a\", \"b\"\n").unwrap(), None);
+    // The semicolon goes before a comment rather than in it
+    assert_eq!(repl.run("print 1 // note\n").unwrap(), None);
+    assert_eq!(repl.run("print 1 /* note */\n").unwrap(), None);
+
+    // Anything else wrong with it is still an error, reported as for the
+    // original entry
+    match repl.run("print (1\n") {
+        Err(Error::Parser(error)) => assert_eq!(error.line(), 5),
+        other => panic!("Expected a parser error, got {:?}", other),
+    }
+    assert!(matches!(repl.run("printer 1\n"), Err(Error::Parser(_))));
+
+    // Scripts still need the semicolon
+    assert!(matches!(
+        jlox::check("print 1 + 2\n"),
+        Err(Error::Parser(_))
+    ));
+}
+
+#[test]
+fn entries_share_an_interpreter() {
+    let mut repl = Repl::new();
+    repl.interpreter()
+        .define_global("answer", Value::Number(42.0));
+    assert_eq!(repl.run("answer / 2\n").unwrap(), Some(Value::Number(21.0)));
+    assert_eq!(repl.run("print answer;\n").unwrap(), None);
+    assert_eq!(repl.run("answer + 1\n").unwrap(), Some(Value::Number(43.0)));
+}
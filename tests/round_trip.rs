@@ -0,0 +1,134 @@
+//! Property test: random well-formed expressions survive being parsed,
+//! printed back out as source and parsed again, and evaluating them never
+//! panics. The generator is a small seeded PRNG, so failures reproduce.
+
+use jlox::{
+    expr::Expr, interpreter::Interpreter, parser::Parser, scanner::Scanner, token::Literal,
+};
+
+/// xorshift64, which is plenty random for picking grammar rules
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+        choices[self.below(choices.len())]
+    }
+}
+
+const OPERATORS: &[&str] = &[
+    ",", "??", "==", "!=", "<", "<=", ">", ">=", "|", "^", "&", "<<", ">>", "+", "-", "*", "/", "%",
+];
+const NAMES: &[&str] = &["a", "b", "len", "type", "string"];
+const NUMBERS: &[&str] = &["0", "1", "2.5", "007", "4.0", "63", "64"];
+const STRINGS: &[&str] = &["\"\"", "\"text\"", "\"a b\""];
+
+/// Random source for an expression nested at most `depth` deep. Joining
+/// expressions with any operator gives another expression, so this is
+/// always well-formed.
+fn generate(rng: &mut Rng, depth: usize) -> String {
+    if depth == 0 {
+        return match rng.below(4) {
+            0 => rng.pick(NUMBERS).into(),
+            1 => rng.pick(STRINGS).into(),
+            2 => rng.pick(&["true", "false", "nil"]).into(),
+            _ => rng.pick(NAMES).into(),
+        };
+    }
+
+    match rng.below(7) {
+        0 | 1 => {
+            let lhs = generate(rng, depth - 1);
+            let operator = rng.pick(OPERATORS);
+            format!("{} {} {}", lhs, operator, generate(rng, depth - 1))
+        }
+        2 => {
+            let lhs = generate(rng, depth - 1);
+            let mhs = generate(rng, depth - 1);
+            format!("{} ? {} : {}", lhs, mhs, generate(rng, depth - 1))
+        }
+        3 => format!("{}{}", rng.pick(&["-", "!"]), generate(rng, depth - 1)),
+        4 => format!("({})", generate(rng, depth - 1)),
+        5 => {
+            let callee = rng.pick(NAMES);
+            let arguments: Vec<String> = (0..rng.below(3))
+                .map(|_| generate(rng, depth - 1))
+                .collect();
+            format!("{}({})", callee, arguments.join(", "))
+        }
+        _ => format!("{}.{}", generate(rng, depth - 1), rng.pick(NAMES)),
+    }
+}
+
+/// Print an expression as Lox source. Groupings keep their parentheses and
+/// nothing else gets any, so parsing the result gives back the same tree.
+fn source(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary { lhs, operator, rhs } | Expr::Logical { lhs, operator, rhs } => {
+            format!("{} {} {}", source(lhs), operator.lexeme, source(rhs))
+        }
+        Expr::Ternary { lhs, mhs, rhs, .. } => {
+            format!("{} ? {} : {}", source(lhs), source(mhs), source(rhs))
+        }
+        Expr::Call {
+            callee, arguments, ..
+        } => format!(
+            "{}({})",
+            source(callee),
+            arguments.iter().map(source).collect::<Vec<_>>().join(", ")
+        ),
+        Expr::Get { object, name } => format!("{}.{}", source(object), name.lexeme),
+        Expr::Grouping { expression } => format!("({})", source(expression)),
+        Expr::Literal { value, lexeme } => match (value.as_ref(), lexeme) {
+            (_, Some(lexeme)) => lexeme.clone(),
+            (Literal::String(s), None) => format!("\"{}\"", s),
+            (other, None) => other.to_string(),
+        },
+        // A space keeps `- -1` from turning into something else
+        Expr::Unary { operator, operand } => format!("{} {}", operator.lexeme, source(operand)),
+        Expr::Variable { name } => name.lexeme.clone(),
+        Expr::Block { .. } => unreachable!("Blocks aren't generated"),
+    }
+}
+
+fn parse(source: &str) -> Expr {
+    let tokens = Scanner::new(source)
+        .scan_tokens()
+        .unwrap_or_else(|errors| panic!("{:?} scanning {}", errors, source));
+    Parser::new(&tokens)
+        .parse()
+        .unwrap_or_else(|error| panic!("{} parsing {}", error, source))
+}
+
+#[test]
+fn print_and_reparse() {
+    let mut rng = Rng(0x5eed_1e55_c0ff_ee00);
+
+    for _ in 0..2000 {
+        let depth = 1 + rng.below(4);
+        let generated = generate(&mut rng, depth);
+        let expr = parse(&generated);
+
+        let printed = source(&expr);
+        let reparsed = parse(&printed);
+        assert!(
+            expr.structurally_eq(&reparsed),
+            "{} was printed as {}",
+            generated,
+            printed
+        );
+
+        // Any error is fine, as long as it is an error rather than a panic
+        let _ = Interpreter::with_output(std::io::sink(), std::io::sink()).evaluate(&expr);
+    }
+}
@@ -0,0 +1,85 @@
+use jlox::{diagnostic::Diagnostic, interpreter::Value, Error};
+
+#[test]
+fn run_valid_program() {
+    assert_eq!(jlox::run("1 + 2 * 3").unwrap(), Value::Number(7.0));
+}
+
+#[test]
+fn run_invalid_programs() {
+    match jlox::run("1 @ 2") {
+        Err(Error::Scanner(errors)) => assert_eq!(errors[0].code(), "E0001"),
+        other => panic!("Expected scanner errors, got {:?}", other),
+    }
+
+    match jlox::run("(1 + 2") {
+        Err(Error::Parser(error)) => assert_eq!(error.code(), "E1001"),
+        other => panic!("Expected a parser error, got {:?}", other),
+    }
+
+    match jlox::run("1 + nil") {
+        Err(Error::Runtime(error)) => {
+            assert_eq!(
+                error.message,
+                "Operands must be two numbers or two strings."
+            )
+        }
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn run_timed_records_phases() {
+    let start = std::time::Instant::now();
+    let (result, timings) = jlox::run_timed("substring(\"timing\", 0, 3)");
+    let elapsed = start.elapsed();
+
+    assert_eq!(result.unwrap(), Value::String("tim".into()));
+    assert!(timings.scan + timings.parse + timings.evaluate <= elapsed);
+}
+
+#[test]
+fn run_timed_skips_phases_after_failure() {
+    let (result, timings) = jlox::run_timed("(1 + 2");
+
+    assert!(matches!(result, Err(Error::Parser(_))));
+    assert_eq!(timings.evaluate, std::time::Duration::ZERO);
+}
+
+#[test]
+fn lint_and_run_program() {
+    let source = "print true ? 1 : \"one\";";
+    let mut codes = vec![];
+    let (result, _) = jlox::lint_and_run_program_timed(source, false, |warnings| {
+        codes = warnings.iter().map(|w| w.code()).collect()
+    });
+    assert!(result.is_ok());
+    assert_eq!(codes, ["W0001"]);
+
+    let (result, timings) =
+        jlox::lint_and_run_program_timed(source, true, |_| panic!("Warnings are an error"));
+    assert!(matches!(result, Err(Error::Warnings(_))));
+    assert_eq!(timings.evaluate, std::time::Duration::ZERO);
+}
+
+#[test]
+fn dump_ast_json() {
+    let json = jlox::dump_ast_json("print 1 + 2;\nf();").unwrap();
+    assert!(json.starts_with(r#"[{"type":"Print","expressions":[{"type":"Binary","#));
+    assert!(json.contains(r#"{"type":"Expression","expression":{"type":"Call","#));
+
+    assert_eq!(jlox::dump_ast_json("").unwrap(), "[]");
+    assert!(matches!(
+        jlox::dump_ast_json("print 1"),
+        Err(Error::Parser(_))
+    ));
+}
+
+#[test]
+fn run_oversized_source() {
+    let source = " ".repeat(jlox::scanner::DEFAULT_MAX_SOURCE_LEN + 1);
+    match jlox::run_program(&source) {
+        Err(Error::Scanner(errors)) => assert_eq!(errors[0].code(), "E0003"),
+        other => panic!("Expected scanner errors, got {:?}", other),
+    }
+}
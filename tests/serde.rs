@@ -0,0 +1,15 @@
+#![cfg(feature = "serde")]
+
+use jlox::{expr::Expr, parser::Parser, scanner::Scanner};
+
+#[test]
+fn expr_json_round_trip() {
+    let tokens = Scanner::new("-(1 + 2) * \"three\" ? nil : !true")
+        .scan_tokens()
+        .unwrap();
+    let expr = Parser::new(&tokens).parse().unwrap();
+
+    let json = serde_json::to_string(&expr).unwrap();
+    let round_tripped: Expr = serde_json::from_str(&json).unwrap();
+    assert_eq!(round_tripped, expr);
+}
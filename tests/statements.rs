@@ -0,0 +1,99 @@
+use jlox::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
+
+/// Run a program and return what it printed
+fn output(source: &str) -> String {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let statements = Parser::new(&tokens).parse_program().unwrap();
+
+    let mut out = Vec::<u8>::new();
+    Interpreter::with_output(&mut out, std::io::sink())
+        .interpret(&statements)
+        .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn print() {
+    assert_eq!(output("print \"hello\";"), "hello\n");
+    assert_eq!(output("print 1 == 1;"), "true\n");
+    assert_eq!(output("print nil; print 2.5;"), "nil\n2.5\n");
+}
+
+#[test]
+fn expression_statements_print_nothing() {
+    assert_eq!(output("1 + 2; len(\"abc\");"), "");
+    assert_eq!(output(""), "");
+}
+
+#[test]
+fn print_numbers() {
+    assert_eq!(output("print 1000000.0;"), "1000000\n");
+    assert_eq!(output("print -0;"), "-0\n");
+    assert_eq!(output("print -0 == 0;"), "true\n");
+    assert_eq!(output("print 1 / 8;"), "0.125\n");
+    assert_eq!(
+        output("print 1000000 * 1000000 * 1000000 * 1000;"),
+        "1000000000000000000000\n"
+    );
+}
+
+#[test]
+fn print_several_values() {
+    assert_eq!(output("print 1, 2, 3;"), "1 2 3\n");
+    assert_eq!(
+        output("print \"a\", nil, 1 < 2 ? \"b\" : \"c\";"),
+        "a nil b\n"
+    );
+    // The comma operator is still there when grouped
+    assert_eq!(output("print (1, 2), 3;"), "2 3\n");
+}
+
+#[test]
+fn block_expression_statements_run_first() {
+    let tokens = Scanner::new("print { print \"inside\"; \"outside\" };")
+        .scan_tokens()
+        .unwrap();
+    let statements = Parser::new(&tokens)
+        .block_expressions(true)
+        .parse_program()
+        .unwrap();
+
+    let mut out = Vec::<u8>::new();
+    Interpreter::with_output(&mut out, std::io::sink())
+        .interpret(&statements)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "inside\noutside\n");
+}
+
+#[test]
+fn print_with_precision() {
+    let output = |source: &str, precision: Option<usize>| {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let statements = Parser::new(&tokens).parse_program().unwrap();
+
+        let mut out = Vec::<u8>::new();
+        Interpreter::with_output(&mut out, std::io::sink())
+            .precision(precision)
+            .interpret(&statements)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    };
+
+    assert_eq!(output("print 1 / 3;", None), "0.3333333333333333\n");
+    assert_eq!(output("print 1 / 3;", Some(3)), "0.333\n");
+    assert_eq!(
+        output("print 2 / 3, 12345, 0.5;", Some(3)),
+        "0.667 12300 0.5\n"
+    );
+    // Strings aren't numbers, however they look
+    assert_eq!(
+        output("print 987, \"1.23456\";", Some(1)),
+        "1000 1.23456\n"
+    );
+}
+
+#[test]
+fn print_chars() {
+    assert_eq!(output("print 'a';"), "a\n");
+    assert_eq!(output("print 'a' + \"bc\", 'x' == \"x\";"), "abc true\n");
+}